@@ -1,4 +1,23 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Names the palette entries that level files can refer to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorName {
+    Walls,
+    Sand,
+    BlueSand,
+    RedSand,
+    GlowSlime,
+    Water,
+}
+
+impl ColorName {
+    /// Particles with these colours glow, lighting their neighbourhood in their colour
+    pub fn is_emissive(&self) -> bool {
+        matches!(self, ColorName::GlowSlime)
+    }
+}
 
 pub struct Colors {
     pub background: Color,
@@ -6,6 +25,8 @@ pub struct Colors {
     pub walls: Color,
     pub blue_sand: Color,
     pub red_sand: Color,
+    pub glow_slime: Color,
+    pub water: Color,
 }
 
 impl Default for Colors {
@@ -17,6 +38,8 @@ impl Default for Colors {
             sand: Color::hsla(163., 0.66, 0.42, 1.),
             blue_sand: Color::hsla(194., 0.66, 0.42, 1.),
             red_sand: Color::hsla(348., 0.66, 0.42, 1.),
+            glow_slime: Color::hsla(75., 0.95, 0.6, 1.),
+            water: Color::hsla(220., 0.7, 0.3, 1.),
         };
 
         #[cfg(not(target_arch = "wasm32"))]
@@ -26,10 +49,25 @@ impl Default for Colors {
             sand: Color::hsla(140., 0.92, 0.67, 1.),
             blue_sand: Color::hsla(211., 0.92, 0.67, 1.),
             red_sand: Color::hsla(11., 0.68, 0.55, 1.),
+            glow_slime: Color::hsla(75., 0.95, 0.6, 1.),
+            water: Color::hsla(220., 0.75, 0.35, 1.),
         };
     }
 }
 
+impl Colors {
+    pub fn get(&self, name: ColorName) -> Color {
+        match name {
+            ColorName::Walls => self.walls,
+            ColorName::Sand => self.sand,
+            ColorName::BlueSand => self.blue_sand,
+            ColorName::RedSand => self.red_sand,
+            ColorName::GlowSlime => self.glow_slime,
+            ColorName::Water => self.water,
+        }
+    }
+}
+
 pub fn to_u8s(color: Color) -> [u8; 3] {
     [
         (color.r() * 255.) as u8,
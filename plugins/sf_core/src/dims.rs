@@ -2,28 +2,112 @@ use std::{iter::Enumerate, ops::Range};
 
 use bevy::math::Vec2;
 
+/// How many cells are shown on screen at once. Levels are this size unless they say otherwise.
+pub const VIEW_SIZE: (u32, u32) = (150, 100);
+
+/// The size of the grid, and how it is drawn to the window. The world is `tex_stride`
+/// units per grid cell, centred on the middle of the grid. A view of `view_w` by `view_h`
+/// cells around `camera` is scaled by `zoom` to fit the window.
 #[derive(Copy, Clone)]
 pub struct Dims {
     pub tex_w: u32,
     pub tex_h: u32,
     pub tex_stride: u32,
-    pub win_w: u32,
-    pub win_h: u32,
+    pub view_w: u32,
+    pub view_h: u32,
+    /// the window size in logical pixels
+    pub win_w: f32,
+    pub win_h: f32,
+    /// window pixels per world unit
+    pub zoom: f32,
+    /// the world position in the middle of the window
+    pub camera: Vec2,
 }
 
-impl From<(u32, u32, u32)> for Dims {
-    fn from(input: (u32, u32, u32)) -> Self {
-        Dims {
-            tex_w: input.0 / input.2,
-            tex_h: input.1 / input.2,
-            tex_stride: input.2,
-            win_w: input.0,
-            win_h: input.1,
-        }
+impl Dims {
+    /// Creates dims for a grid the size of the view
+    pub fn new(view_w: u32, view_h: u32, tex_stride: u32) -> Self {
+        let mut dims = Dims {
+            tex_w: view_w,
+            tex_h: view_h,
+            tex_stride,
+            view_w,
+            view_h,
+            win_w: 0.,
+            win_h: 0.,
+            zoom: 1.,
+            camera: Vec2::ZERO,
+        };
+
+        dims.fit_window((view_w * tex_stride) as f32, (view_h * tex_stride) as f32);
+        dims
+    }
+
+    /// The size of the grid in world units
+    pub fn world_w(&self) -> f32 {
+        (self.tex_w * self.tex_stride) as f32
+    }
+
+    pub fn world_h(&self) -> f32 {
+        (self.tex_h * self.tex_stride) as f32
+    }
+
+    /// Changes the size of the grid, the map needs resizing to match
+    pub fn set_grid_size(&mut self, size: (u32, u32)) {
+        self.tex_w = size.0;
+        self.tex_h = size.1;
+        self.camera = self.clamp_camera(self.camera);
+    }
+
+    /// Picks the largest zoom that fits the view in the window, rounded down so
+    /// each cell covers a whole number of pixels. Whatever is left over is letterboxed.
+    pub fn fit_window(&mut self, win_w: f32, win_h: f32) {
+        self.win_w = win_w;
+        self.win_h = win_h;
+
+        let stride = self.tex_stride as f32;
+        let fit =
+            (win_w / (self.view_w as f32 * stride)).min(win_h / (self.view_h as f32 * stride));
+        let cell_pixels = fit * stride;
+        self.zoom = if cell_pixels >= 1. {
+            cell_pixels.floor() / stride
+        } else {
+            fit.max(f32::EPSILON)
+        };
+
+        self.camera = self.clamp_camera(self.camera);
+    }
+
+    /// How much of the world the window shows, in world units
+    pub fn visible_size(&self) -> Vec2 {
+        Vec2::new(self.win_w, self.win_h) / self.zoom
+    }
+
+    /// The bottom left and top right corners of the visible area, in grid coordinates
+    pub fn visible_cells(&self) -> (Vec2, Vec2) {
+        let to_grid = |world: Vec2| {
+            (world + Vec2::new(self.world_w(), self.world_h()) / 2.) / self.tex_stride as f32
+        };
+        let half = self.visible_size() / 2.;
+
+        (to_grid(self.camera - half), to_grid(self.camera + half))
+    }
+
+    /// Keeps the camera from showing past the edges of the grid. Grids smaller than
+    /// the window stay in the middle of it.
+    pub fn clamp_camera(&self, pos: Vec2) -> Vec2 {
+        let visible = self.visible_size();
+        let clamp = |pos: f32, world: f32, visible: f32| {
+            let limit = (world - visible).max(0.) / 2.;
+            pos.max(-limit).min(limit)
+        };
+
+        Vec2::new(
+            clamp(pos.x, self.world_w(), visible.x),
+            clamp(pos.y, self.world_h(), visible.y),
+        )
     }
-}
 
-impl Dims {
     pub fn to_tex_index(&self, x: u32, y: u32) -> usize {
         ((x + (self.tex_h - y - 1) * self.tex_w) * 4) as usize
     }
@@ -46,26 +130,32 @@ impl Dims {
         (4 * self.tex_h * self.tex_w) as usize
     }
 
+    /// Converts a window position (from the bottom left, in logical pixels) to a world position
+    pub fn screen_to_world(&self, pos: Vec2) -> Vec2 {
+        (pos - Vec2::new(self.win_w, self.win_h) / 2.) / self.zoom + self.camera
+    }
+
+    /// Converts a window position to (fractional) grid coordinates, which may be
+    /// outside the grid when the cursor is over the letterboxing
     pub fn screen_to_grid(&self, pos: Vec2) -> Vec2 {
+        let world = self.screen_to_world(pos);
         Vec2::new(
-            self.tex_w as f32 * (pos.x / (self.win_w as f32)),
-            self.tex_h as f32 * (pos.y / (self.win_h as f32)),
+            (world.x + self.world_w() / 2.) / self.tex_stride as f32,
+            (world.y + self.world_h() / 2.) / self.tex_stride as f32,
         )
     }
 
     pub fn world_to_grid(&self, pos: Vec2) -> (u32, u32) {
         (
-            (self.tex_w as f32 * (pos.x + (self.win_w as f32) / 2.) / self.win_w as f32).floor()
-                as u32,
-            (self.tex_h as f32 * (pos.y + (self.win_h as f32) / 2.) / self.win_h as f32).floor()
-                as u32,
+            ((pos.x + self.world_w() / 2.) / self.tex_stride as f32).floor() as u32,
+            ((pos.y + self.world_h() / 2.) / self.tex_stride as f32).floor() as u32,
         )
     }
 
     pub fn grid_to_world(&self, x: u32, y: u32) -> Vec2 {
         Vec2::new(
-            (self.win_w as f32) * (x as f32 / self.tex_w as f32) - (self.win_w as f32 / 2.),
-            (self.win_h as f32) * (y as f32 / self.tex_h as f32) - (self.win_h as f32 / 2.),
+            (x * self.tex_stride) as f32 - self.world_w() / 2.,
+            (y * self.tex_stride) as f32 - self.world_h() / 2.,
         )
     }
 }
@@ -1,57 +1,47 @@
-use bevy::prelude::{Color, Vec2};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone)]
-pub enum ParticleType {
-    Obstacle,
-    // Trap,
-    Sand,
-    Liquid,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub struct Particle {
-    pub pos: Vec2,
-    pub vel: Vec2,
-    pub color: Color,
-    pub particle_type: ParticleType,
-    pub next_update: f64,
-    pub is_left_first: bool,
-}
-
-impl Particle {
-    pub fn move_entity(&mut self) {
-        self.pos += self.vel;
-    }
-
-    pub fn x(&self) -> u32 {
-        self.pos.x.floor() as u32
-    }
-
-    pub fn y(&self) -> u32 {
-        self.pos.y.floor() as u32
-    }
+use crate::colors::ColorName;
 
-    pub fn get_next_pos(&mut self) -> (u32, u32) {
-        (
-            (self.pos.x.floor() + self.vel.x) as u32,
-            (self.pos.y.floor() + self.vel.y) as u32,
-        )
-    }
-}
+pub use sf_sim::{Particle, ParticleType};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Spawner {
     pub pos: (u32, u32),
     pub spawn_limit: u32,
     pub spawn_delay: f64,
-    pub initial_vel: Vec2,
-    pub color: Color,
+    #[serde(default = "falling")]
+    pub initial_vel: (i32, i32),
+    pub color: ColorName,
+    #[serde(skip)]
     pub next_spawn: f64,
     pub particle_type: ParticleType,
+    /// the strength of the light the spawner gives off, if any
+    #[serde(default)]
+    pub light: Option<f32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Sink {
     pub pos: (u32, u32),
     pub sink_rate: f64,
+    #[serde(skip)]
     pub next_sink: f64,
+    #[serde(default = "unlimited")]
     pub sink_limit: u32,
 }
+
+/// Gives off light around the entity's `Position`
+#[derive(Debug, Clone)]
+pub struct LightEmitter {
+    pub strength: f32,
+    pub color: [u8; 3],
+    pub falloff: f32,
+}
+
+fn falling() -> (i32, i32) {
+    (0, -1)
+}
+
+fn unlimited() -> u32 {
+    u32::MAX
+}
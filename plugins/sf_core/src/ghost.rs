@@ -0,0 +1,186 @@
+//! The fastest finish of each level is kept so a ghost can race the player on later
+//! attempts. On native builds the best runs are saved next to the replays, so they
+//! last between sessions.
+//!
+//! Ghost files hold a header followed by the player's position on each tick.
+
+use std::collections::{hash_map::Entry, HashMap};
+
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+
+/// How see-through the ghost is
+pub const GHOST_ALPHA: f32 = 0.35;
+
+const MAGIC: &[u8; 4] = b"SFG1";
+const HEADER_LEN: usize = 8;
+const POINT_LEN: usize = 8;
+
+/// A translucent copy of the player retracing the fastest finish of the level,
+/// one position per gameplay tick
+pub struct Ghost {
+    pub path: Vec<(u32, u32)>,
+}
+
+/// The path the player has taken through the current level, and the fastest
+/// finish of each level so far
+#[derive(Default)]
+pub struct GhostRuns {
+    /// the player's sprite sheet, set when the player is spawned
+    pub atlas: Handle<TextureAtlas>,
+    level: Option<u32>,
+    current: Vec<(u32, u32)>,
+    best: HashMap<u32, Vec<(u32, u32)>>,
+}
+
+impl GhostRuns {
+    /// Starts recording a new attempt at the given level, loading the best run from
+    /// disk if this is the first attempt. Play tests pass None as they don't have a
+    /// level number of their own.
+    pub fn start(&mut self, level: Option<u32>) {
+        self.level = level;
+        self.current.clear();
+
+        if let Some(level) = level {
+            if let Entry::Vacant(entry) = self.best.entry(level) {
+                if let Some(path) = load_best(level) {
+                    entry.insert(path);
+                }
+            }
+        }
+    }
+
+    pub fn record(&mut self, pos: (u32, u32)) {
+        if self.level.is_some() {
+            self.current.push(pos);
+        }
+    }
+
+    /// Keeps the current attempt if it is the fastest finish of the level so far
+    pub fn finish(&mut self) {
+        let level = match self.level.take() {
+            Some(level) => level,
+            None => return,
+        };
+
+        let current = std::mem::take(&mut self.current);
+        let is_best = match self.best.get(&level) {
+            Some(best) => current.len() < best.len(),
+            None => true,
+        };
+
+        if is_best && !current.is_empty() {
+            println!("New best time for level {}: {} ticks", level, current.len());
+            save_best(level, &current);
+            self.best.insert(level, current);
+        }
+    }
+
+    /// The fastest finish of the given level, if it has been finished
+    pub fn best(&self, level: u32) -> Option<&Vec<(u32, u32)>> {
+        self.best.get(&level)
+    }
+}
+
+pub fn path_to_bytes(level: u32, path: &[(u32, u32)]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + path.len() * POINT_LEN);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&level.to_le_bytes());
+
+    for (x, y) in path {
+        bytes.extend_from_slice(&x.to_le_bytes());
+        bytes.extend_from_slice(&y.to_le_bytes());
+    }
+
+    bytes
+}
+
+/// Reads a ghost file, returning the level it is for and the path
+pub fn path_from_bytes(bytes: &[u8]) -> Result<(u32, Vec<(u32, u32)>)> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+        bail!("not a ghost file");
+    }
+
+    let points = bytes[HEADER_LEN..].chunks_exact(POINT_LEN);
+    if !points.remainder().is_empty() {
+        bail!("ghost file is truncated");
+    }
+
+    let u32_at = |from: &[u8]| u32::from_le_bytes([from[0], from[1], from[2], from[3]]);
+    let path = points
+        .map(|point| (u32_at(&point[..4]), u32_at(&point[4..])))
+        .collect();
+
+    Ok((u32_at(&bytes[4..8]), path))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn ghost_file(level: u32) -> std::path::PathBuf {
+    std::path::Path::new(crate::replay::REPLAY_DIR).join(format!("level-{}.ghost", level))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_best(level: u32) -> Option<Vec<(u32, u32)>> {
+    let file = ghost_file(level);
+    let bytes = std::fs::read(&file).ok()?;
+
+    match path_from_bytes(&bytes) {
+        Ok((file_level, path)) if file_level == level && !path.is_empty() => Some(path),
+        Ok(_) => {
+            println!("Ignoring {}, it isn't for level {}", file.display(), level);
+            None
+        }
+        Err(e) => {
+            println!("Unable to load ghost from {}: {}", file.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_best(level: u32, path: &[(u32, u32)]) {
+    let file = ghost_file(level);
+    let result = std::fs::create_dir_all(crate::replay::REPLAY_DIR)
+        .and_then(|_| std::fs::write(&file, path_to_bytes(level, path)));
+
+    if let Err(e) = result {
+        println!("Unable to save ghost to {}: {}", file.display(), e);
+    }
+}
+
+// browsers only keep ghosts for the session
+#[cfg(target_arch = "wasm32")]
+fn load_best(_level: u32) -> Option<Vec<(u32, u32)>> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_best(_level: u32, _path: &[(u32, u32)]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_paths() {
+        let path = vec![(0, 0), (1, 2), (u32::MAX, 7)];
+        let bytes = path_to_bytes(12, &path);
+
+        assert_eq!(bytes.len(), HEADER_LEN + 3 * POINT_LEN);
+        assert_eq!(path_from_bytes(&bytes).unwrap(), (12, path));
+        assert_eq!(
+            path_from_bytes(&path_to_bytes(3, &[])).unwrap(),
+            (3, vec![])
+        );
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = path_to_bytes(1, &[(4, 5)]);
+        assert!(path_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        bytes[0] = b'X';
+        assert!(path_from_bytes(&bytes).is_err());
+        assert!(path_from_bytes(b"SFG1").is_err());
+    }
+}
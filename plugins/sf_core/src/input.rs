@@ -23,7 +23,7 @@ pub fn input_capture(
     }
 
     for ev in evr_click.iter() {
-        if ev.button == MouseButton::Right {
+        if ev.button == MouseButton::Left {
             input.mouse_down = ev.state.is_pressed();
         }
     }
@@ -0,0 +1,113 @@
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::{level_image::apply_image, levels::Level};
+
+/// The manifest listing the levels in the order they are played
+pub const LEVEL_MANIFEST: &str = "levels/levels.manifest";
+
+/// Holds the manifest handle so the level files stay loaded
+pub struct LevelAssets {
+    pub manifest: Handle<LevelManifest>,
+}
+
+#[derive(TypeUuid)]
+#[uuid = "36510476-a43e-4b8e-abf4-e1f99ff2511c"]
+pub struct LevelManifest {
+    pub levels: Vec<Handle<Level>>,
+}
+
+/// The on-disk format of the manifest, level paths are relative to the manifest
+#[derive(Deserialize)]
+struct ManifestFile {
+    levels: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let mut level: Level = ron::de::from_bytes(bytes)?;
+
+            // painted levels take their layout from a PNG next to the level file. The PNG
+            // is also loaded as a texture so it is watched for changes, see `level_image_reloader`
+            let mut dependencies = vec![];
+            if let Some(image) = level.image.clone() {
+                let path = relative_to(load_context, &image);
+                let image_bytes = load_context.read_asset_bytes(&path).await?;
+                apply_image(&mut level, &image_bytes)?;
+
+                level.image_path = Some(path.clone());
+                dependencies.push(AssetPath::new(path, None));
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(level).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level"]
+    }
+}
+
+#[derive(Default)]
+pub struct LevelManifestLoader;
+
+impl AssetLoader for LevelManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let file: ManifestFile = ron::de::from_bytes(bytes)?;
+            let paths: Vec<AssetPath> = file
+                .levels
+                .iter()
+                .map(|name| AssetPath::new(relative_to(load_context, name), None))
+                .collect();
+
+            let levels = paths
+                .iter()
+                .map(|path| load_context.get_handle(path.clone()))
+                .collect();
+
+            load_context.set_default_asset(
+                LoadedAsset::new(LevelManifest { levels }).with_dependencies(paths),
+            );
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest"]
+    }
+}
+
+/// Resolves a path given in a level or manifest file against that file's directory
+fn relative_to(load_context: &LoadContext, name: &str) -> PathBuf {
+    load_context
+        .path()
+        .parent()
+        .map(|dir| dir.join(name))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+pub fn load_level_manifest(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(LevelAssets {
+        manifest: asset_server.load(LEVEL_MANIFEST),
+    });
+}
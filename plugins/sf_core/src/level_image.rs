@@ -0,0 +1,249 @@
+//! Builds level layouts from PNGs so levels can be painted rather than typed out.
+//! Each pixel is one grid cell, with the bottom row of the image at y = 0.
+//! Pixels that don't match a colour key (or are transparent) are left empty.
+
+use anyhow::{bail, Result};
+
+use crate::{
+    colors::ColorName,
+    entity::{ParticleType, Sink, Spawner},
+    levels::{Level, LevelParticle, Wall},
+};
+
+pub const WALL_KEY: [u8; 3] = [255, 255, 255];
+pub const SAND_KEY: [u8; 3] = [255, 255, 0];
+pub const LIQUID_KEY: [u8; 3] = [0, 0, 255];
+pub const SAND_SPAWNER_KEY: [u8; 3] = [255, 128, 0];
+pub const LIQUID_SPAWNER_KEY: [u8; 3] = [0, 255, 255];
+pub const WATER_KEY: [u8; 3] = [0, 128, 255];
+pub const WATER_SPAWNER_KEY: [u8; 3] = [128, 128, 255];
+pub const SINK_KEY: [u8; 3] = [255, 0, 255];
+pub const PLAYER_KEY: [u8; 3] = [0, 255, 0];
+
+/// Replaces the layout of the level (spawn point, walls, particles, spawners and sinks)
+/// with the contents of the given PNG. Fails if the level sets a size that doesn't
+/// match the image.
+pub fn apply_image(level: &mut Level, bytes: &[u8]) -> Result<()> {
+    let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)?.to_rgba8();
+    let (w, h) = image.dimensions();
+
+    check_size(level, w, h)?;
+    apply_pixels(level, w, h, image.as_raw())
+}
+
+/// Fails if the level already has a size and it isn't `w` x `h`
+pub fn check_size(level: &Level, w: u32, h: u32) -> Result<()> {
+    if let Some((level_w, level_h)) = level.size {
+        if (level_w, level_h) != (w, h) {
+            bail!(
+                "level is {}x{} but its image is {}x{}",
+                level_w,
+                level_h,
+                w,
+                h
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the layout of the level with the given RGBA pixels, top row first,
+/// resizing the level to match. Use `check_size` first to keep the level's size.
+pub fn apply_pixels(level: &mut Level, w: u32, h: u32, rgba: &[u8]) -> Result<()> {
+    if rgba.len() != (w * h * 4) as usize {
+        bail!("expected {}x{} RGBA pixels, got {} bytes", w, h, rgba.len());
+    }
+
+    let mut walls = vec![];
+    level.particles.clear();
+    level.spawners.clear();
+    level.sinks.clear();
+    level.size = Some((w, h));
+
+    for (idx, pixel) in rgba.chunks_exact(4).enumerate() {
+        if pixel[3] == 0 {
+            continue;
+        }
+
+        let (px, py) = (idx as u32 % w, idx as u32 / w);
+        let pos = (px, h - py - 1);
+        match [pixel[0], pixel[1], pixel[2]] {
+            WALL_KEY => walls.push(pos),
+            SAND_KEY => level.particles.push(LevelParticle {
+                pos,
+                particle_type: ParticleType::Sand,
+                color: ColorName::Sand,
+            }),
+            LIQUID_KEY => level.particles.push(LevelParticle {
+                pos,
+                particle_type: ParticleType::Liquid,
+                color: ColorName::BlueSand,
+            }),
+            SAND_SPAWNER_KEY => {
+                level
+                    .spawners
+                    .push(default_spawner(pos, ParticleType::Sand, ColorName::Sand))
+            }
+            LIQUID_SPAWNER_KEY => level.spawners.push(default_spawner(
+                pos,
+                ParticleType::Liquid,
+                ColorName::BlueSand,
+            )),
+            WATER_KEY => level.particles.push(LevelParticle {
+                pos,
+                particle_type: ParticleType::Water,
+                color: ColorName::Water,
+            }),
+            WATER_SPAWNER_KEY => {
+                level
+                    .spawners
+                    .push(default_spawner(pos, ParticleType::Water, ColorName::Water))
+            }
+            SINK_KEY => level.sinks.push(Sink {
+                pos,
+                sink_rate: 0.5,
+                next_sink: 0.,
+                sink_limit: u32::MAX,
+            }),
+            PLAYER_KEY => level.player_spawn = pos,
+            _ => {}
+        }
+    }
+
+    level.walls = vec![Wall::Points(walls)];
+
+    Ok(())
+}
+
+fn default_spawner(pos: (u32, u32), particle_type: ParticleType, color: ColorName) -> Spawner {
+    Spawner {
+        pos,
+        spawn_limit: 200,
+        spawn_delay: 0.1,
+        initial_vel: (0, -1),
+        color,
+        next_spawn: 0.,
+        particle_type,
+        light: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+
+    const EMPTY: [u8; 4] = [0, 0, 0, 0];
+
+    fn level() -> Level {
+        ron::de::from_str(
+            "(player_slime_target: 0, message: \"\", starting_light: 0, max_light: 0, light_decay: 0.0)",
+        )
+        .unwrap()
+    }
+
+    fn key(rgb: [u8; 3]) -> [u8; 4] {
+        [rgb[0], rgb[1], rgb[2], 255]
+    }
+
+    /// Encodes the rows (top row first) as a PNG
+    fn png(rows: &[&[[u8; 4]]]) -> Vec<u8> {
+        let (w, h) = (rows[0].len() as u32, rows.len() as u32);
+        let image = RgbaImage::from_fn(w, h, |x, y| Rgba(rows[y as usize][x as usize]));
+
+        let mut bytes = vec![];
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut bytes, ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn maps_colour_keys_with_the_bottom_row_at_y_zero() {
+        let bytes = png(&[
+            &[key(WALL_KEY), EMPTY, key(SAND_KEY)],
+            &[key(PLAYER_KEY), key(LIQUID_KEY), key(SINK_KEY)],
+        ]);
+
+        let mut level = level();
+        apply_image(&mut level, &bytes).unwrap();
+
+        assert_eq!(level.size, Some((3, 2)));
+        assert_eq!(level.player_spawn, (0, 0));
+
+        let walls: Vec<_> = level.walls.iter().flat_map(|wall| wall.points()).collect();
+        assert_eq!(walls, vec![(0, 1)]);
+
+        assert_eq!(level.particles.len(), 2);
+        assert_eq!(level.particles[0].pos, (2, 1));
+        assert_eq!(level.particles[0].particle_type, ParticleType::Sand);
+        assert_eq!(level.particles[1].pos, (1, 0));
+        assert_eq!(level.particles[1].particle_type, ParticleType::Liquid);
+
+        assert_eq!(level.sinks.len(), 1);
+        assert_eq!(level.sinks[0].pos, (2, 0));
+        assert!(level.spawners.is_empty());
+    }
+
+    #[test]
+    fn maps_spawner_keys() {
+        let bytes = png(&[&[
+            key(SAND_SPAWNER_KEY),
+            key(LIQUID_SPAWNER_KEY),
+            key(WATER_SPAWNER_KEY),
+            key(WATER_KEY),
+            [1, 2, 3, 255],
+        ]]);
+
+        let mut level = level();
+        apply_image(&mut level, &bytes).unwrap();
+
+        let spawners: Vec<_> = level
+            .spawners
+            .iter()
+            .map(|spawner| (spawner.pos, spawner.particle_type))
+            .collect();
+        assert_eq!(
+            spawners,
+            vec![
+                ((0, 0), ParticleType::Sand),
+                ((1, 0), ParticleType::Liquid),
+                ((2, 0), ParticleType::Water),
+            ]
+        );
+
+        // unknown colours are left empty
+        assert_eq!(level.particles.len(), 1);
+        assert_eq!(level.particles[0].pos, (3, 0));
+    }
+
+    #[test]
+    fn replaces_the_previous_layout() {
+        let mut level = level();
+        apply_image(&mut level, &png(&[&[key(SAND_KEY), key(SAND_KEY)]])).unwrap();
+        apply_pixels(&mut level, 1, 1, &key(SINK_KEY)).unwrap();
+
+        assert_eq!(level.size, Some((1, 1)));
+        assert!(level.particles.is_empty());
+        assert_eq!(level.sinks.len(), 1);
+    }
+
+    #[test]
+    fn rejects_an_image_that_does_not_match_the_level_size() {
+        let mut level = level();
+        level.size = Some((4, 4));
+
+        assert!(apply_image(&mut level, &png(&[&[key(SAND_KEY)]])).is_err());
+        assert!(level.particles.is_empty());
+        assert!(check_size(&level, 1, 1).is_err());
+        assert!(check_size(&level, 4, 4).is_ok());
+    }
+
+    #[test]
+    fn rejects_the_wrong_number_of_pixels() {
+        let mut level = level();
+        assert!(apply_pixels(&mut level, 2, 2, &key(SAND_KEY)).is_err());
+        assert!(apply_image(&mut level, b"not a png").is_err());
+    }
+}
@@ -1,369 +1,216 @@
 use crate::{
-    colors::{to_u8s, Colors},
-    dims::Dims,
-    entity::{Particle, ParticleType, Sink, Spawner},
+    colors::{to_u8s, ColorName, Colors},
+    dims::{Dims, VIEW_SIZE},
+    entity::{LightEmitter, Particle, ParticleType, Sink, Spawner},
+    ghost::{Ghost, GhostRuns, GHOST_ALPHA},
+    level_assets::{LevelAssets, LevelManifest},
     map::Map,
-    GameState, LightingTarget, Player, Position, StaticEntity, TimedDespawn,
+    player_config::{PlayerConfig, PlayerConfigFiles, PlayerConfigOverrides},
+    render::lights::DEFAULT_FALLOFF,
+    sim::SimRng,
+    GameState, Hitbox, LightingTarget, Player, Position, TimedDespawn,
 };
-use bevy::prelude::*;
-use rand::{thread_rng, Rng};
-use std::ops::Range;
+use bevy::{prelude::*, reflect::TypeUuid};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, path::PathBuf};
 
 pub struct NextLevel(pub u32);
 pub struct LevelMessage(pub String);
 
-pub struct Wall {
-    pub points: Vec<(u32, u32)>,
+/// When set, `spawn_level` plays this level instead of the next one in the manifest.
+/// Used to play test levels from the editor.
+#[derive(Default)]
+pub struct PlaytestLevel {
+    pub level: Option<Level>,
+    /// The asset path the level is saved to, so changes on disk replace the play test
+    pub path: Option<String>,
+}
+
+/// A run of wall cells. Ranges are half open, i.e. (0, 15) covers 0 to 14.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Wall {
+    Horizontal { x_range: (u32, u32), y: u32 },
+    Vertical { x: u32, y_range: (u32, u32) },
+    Points(Vec<(u32, u32)>),
 }
 
 impl Wall {
     pub fn from_x_range(x_range: Range<u32>, y: u32) -> Self {
-        Wall {
-            points: x_range.map(|x| (x, y)).collect::<Vec<_>>(),
+        Wall::Horizontal {
+            x_range: (x_range.start, x_range.end),
+            y,
         }
     }
+
     pub fn from_y_range(x: u32, y_range: Range<u32>) -> Self {
-        Wall {
-            points: y_range.map(|y| (x, y)).collect::<Vec<_>>(),
+        Wall::Vertical {
+            x,
+            y_range: (y_range.start, y_range.end),
+        }
+    }
+
+    pub fn points(&self) -> Vec<(u32, u32)> {
+        match self {
+            Wall::Horizontal { x_range, y } => (x_range.0..x_range.1).map(|x| (x, *y)).collect(),
+            Wall::Vertical { x, y_range } => (y_range.0..y_range.1).map(|y| (*x, y)).collect(),
+            Wall::Points(points) => points.clone(),
         }
     }
 }
 
+/// A loose particle that is in place when the level starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelParticle {
+    pub pos: (u32, u32),
+    pub particle_type: ParticleType,
+    pub color: ColorName,
+}
+
+/// A light fixed in place for the whole level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelLight {
+    pub pos: (u32, u32),
+    pub strength: f32,
+    /// lights are white unless given a colour
+    #[serde(default)]
+    pub color: Option<ColorName>,
+    /// how sharply the light fades towards its edge, see `lights::DEFAULT_FALLOFF`
+    #[serde(default = "default_falloff")]
+    pub falloff: f32,
+}
+
+fn default_falloff() -> f32 {
+    DEFAULT_FALLOFF
+}
+
+/// A level as described by a `.level` file in `assets/levels`
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
+#[uuid = "9f68ac39-d459-400e-82f6-6625b3a89e44"]
 pub struct Level {
+    /// a PNG (relative to the level file) to build the layout from, see `level_image`
+    #[serde(default)]
+    pub image: Option<String>,
+    /// the asset path of `image`, filled in when the level is loaded
+    #[serde(skip)]
+    pub image_path: Option<PathBuf>,
+    /// the size of the grid in cells, levels bigger than the screen scroll to follow the player
+    #[serde(default)]
+    pub size: Option<(u32, u32)>,
+
+    #[serde(default)]
     pub player_spawn: (u32, u32),
     pub player_slime_target: u32,
+    #[serde(default)]
     pub walls: Vec<Wall>,
+    #[serde(default)]
+    pub particles: Vec<LevelParticle>,
+    #[serde(default)]
     pub spawners: Vec<Spawner>,
+    #[serde(default)]
     pub sinks: Vec<Sink>,
+    #[serde(default)]
+    pub lights: Vec<LevelLight>,
     pub message: String,
 
     pub starting_light: u32,
     pub max_light: u32,
     pub light_decay: f64,
+
+    /// changes to the default player physics for this level
+    #[serde(default)]
+    pub player: PlayerConfigOverrides,
 }
 
 impl Level {
-    pub fn get_level(colours: &Res<Colors>, level: u32) -> Option<Self> {
-        println!("Generating level {}", level);
-        if level == 1 {
-            Some(Level::level_one(colours))
-        } else if level == 2 {
-            Some(Level::level_two(colours))
-        } else if level == 3 {
-            Some(Level::level_three(colours))
-        } else if level == 4 {
-            Some(Level::level_four(colours))
-        } else {
-            None
-        }
-    }
-
-    pub fn level_one(colours: &Res<Colors>) -> Self {
-        Level {
-            player_spawn: (5, 93),
-            player_slime_target: 40,
-            starting_light: 170,
-            max_light: 170,
-            light_decay: 0.5,
-            message: "Collect enough slime to exit at the bottom of the level...".into(),
-            walls: vec![
-                // starting
-                Wall::from_x_range(0..15, 90),
-                // steps down
-                Wall::from_x_range(20..35, 70),
-                Wall::from_x_range(40..45, 60),
-                // slime catcher
-                Wall::from_x_range(57..65, 50),
-                Wall::from_y_range(55, 50..54),
-                Wall::from_y_range(65, 50..54),
-                // slime catcher below
-                Wall::from_x_range(50..70, 30),
-                Wall::from_y_range(50, 30..33),
-                Wall::from_y_range(70, 30..33),
-            ],
-            spawners: vec![
-                Spawner {
-                    pos: (62, 95),
-                    spawn_limit: 500,
-                    spawn_delay: 0.01,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Sand,
-                },
-                Spawner {
-                    pos: (61, 95),
-                    spawn_limit: 1000,
-                    spawn_delay: 0.02,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.blue_sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Liquid,
-                },
-            ],
-            sinks: vec![],
-        }
-    }
-
-    pub fn level_two(colours: &Res<Colors>) -> Self {
-        Level {
-            player_spawn: (5, 5),
-            player_slime_target: 100,
-            starting_light: 30,
-            max_light: 50,
-            light_decay: 1.24,
-            message: "Slime powers your light, finish before your it gets dark".into(),
-            walls: vec![
-                // starting
-                Wall::from_x_range(0..15, 3),
-                // bottom bucket
-                Wall::from_x_range(55..65, 0),
-                Wall::from_y_range(55, 0..23),
-                Wall::from_y_range(65, 0..5),
-                // steps up
-                Wall::from_x_range(10..25, 12),
-                Wall::from_x_range(30..39, 20),
-                Wall::from_y_range(39, 20..23),
-                Wall::from_x_range(39..60, 23),
-                Wall::from_y_range(60, 23..40),
-                // switch back stairs with some small pools
-                Wall::from_x_range(27..39, 32),
-                // first bucket
-                Wall::from_x_range(18..22, 38),
-                Wall::from_y_range(18, 38..42),
-                Wall::from_y_range(22, 38..42),
-                // second bucket
-                Wall::from_x_range(10..15, 45),
-                Wall::from_y_range(10, 45..49),
-                Wall::from_y_range(15, 45..47),
-                // third bucket
-                Wall::from_x_range(20..26, 52),
-                Wall::from_y_range(20, 52..54),
-                Wall::from_y_range(26, 52..56),
-                // walkway from third bucket with drop
-                Wall::from_x_range(30..42, 60),
-                Wall::from_y_range(42, 60..65),
-                Wall::from_x_range(47..60, 58),
-                //walkway from bottom bucket to right
-                Wall::from_x_range(65..70, 7),
-                Wall::from_x_range(73..78, 15),
-                Wall::from_x_range(82..89, 23),
-                Wall::from_y_range(89, 15..28),
-                Wall::from_x_range(89..105, 17),
-            ],
-            spawners: vec![
-                Spawner {
-                    pos: (62, 95),
-                    spawn_limit: 500,
-                    spawn_delay: 0.5,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.blue_sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Liquid,
-                },
-                Spawner {
-                    pos: (62, 95),
-                    spawn_limit: 50,
-                    spawn_delay: 0.01,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.blue_sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Liquid,
-                },
-                Spawner {
-                    pos: (15, 21),
-                    spawn_limit: 3,
-                    spawn_delay: 0.01,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Sand,
-                },
-                Spawner {
-                    pos: (20, 46),
-                    spawn_limit: 9,
-                    spawn_delay: 0.01,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Sand,
-                },
-                Spawner {
-                    pos: (12, 52),
-                    spawn_limit: 9,
-                    spawn_delay: 0.01,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.red_sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Sand,
-                },
-                Spawner {
-                    pos: (24, 58),
-                    spawn_limit: 9,
-                    spawn_delay: 0.01,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Sand,
-                },
-                Spawner {
-                    pos: (92, 30),
-                    spawn_limit: 200,
-                    spawn_delay: 0.3,
-                    initial_vel: Vec2::new(0., -1.),
-                    color: colours.red_sand.clone(),
-                    next_spawn: 0.,
-                    particle_type: ParticleType::Sand,
-                },
-            ],
-            sinks: vec![
-                Sink {
-                    pos: (66, 0),
-                    sink_rate: 0.5,
-                    next_sink: 0.,
-                    sink_limit: u32::MAX,
-                },
-                Sink {
-                    pos: (54, 0),
-                    sink_rate: 0.5,
-                    next_sink: 0.,
-                    sink_limit: u32::MAX,
-                },
-            ],
-        }
-    }
-
-    pub fn level_three(colours: &Res<Colors>) -> Self {
-        let mut walls = vec![
-            // starting
-            Wall::from_x_range(0..8, 49),
-        ];
-
-        let mut spawners: Vec<Spawner> = vec![];
-
-        let plat = 14;
-
-        // build steps
-        for x in 1..=7 {
-            walls.push(Wall::from_x_range(x * plat..x * plat + 5, 50));
-            walls.push(Wall::from_x_range(x * plat + 6..x * plat + 11, 45));
-
-            spawners.push(Spawner {
-                pos: (x * plat + 8, 55),
-                spawn_limit: 5,
-                spawn_delay: 0.01,
-                initial_vel: Vec2::new(0., -1.),
-                color: colours.red_sand.clone(),
-                next_spawn: 0.,
-                particle_type: ParticleType::Sand,
-            })
-        }
-
-        Level {
-            player_spawn: (5, 50),
-            player_slime_target: 20,
-            starting_light: 20,
-            max_light: 20,
-            light_decay: 1.5,
-            message: "Quickly now!".into(),
-            walls,
-            spawners,
-            sinks: vec![],
-        }
-    }
-
-    pub fn level_four(colours: &Res<Colors>) -> Self {
-        let mut walls = vec![
-            // starting
-            Wall::from_x_range(0..25, 4),
-            Wall::from_y_range(25, 0..62),
-        ];
-
-        for x in 1..7 {
-            walls.push(Wall::from_x_range(
-                if x % 2 == 1 { 0..10 } else { 15..25 },
-                8 * x + 5,
-            ));
-        }
-
-        walls.push(Wall::from_y_range(32, 14..90));
-        for x in 1..10 {
-            walls.push(Wall::from_x_range(
-                if x % 2 == 1 { 26..30 } else { 28..32 },
-                6 * x + 5,
-            ));
-        }
-
-        walls.push(Wall::from_x_range(26..32, 0));
-        walls.push(Wall::from_y_range(32, 0..6));
-
-        let spawners: Vec<Spawner> = vec![
-            Spawner {
-                pos: (27, 99),
-                spawn_limit: 50,
-                spawn_delay: 0.5,
-                initial_vel: Vec2::new(0., -1.),
-                color: colours.sand.clone(),
-                next_spawn: 0.,
-                particle_type: ParticleType::Liquid,
-            },
-            Spawner {
-                pos: (31, 99),
-                spawn_limit: 50,
-                spawn_delay: 0.5,
-                initial_vel: Vec2::new(0., -1.),
-                color: colours.red_sand.clone(),
-                next_spawn: 0.,
-                particle_type: ParticleType::Liquid,
-            },
-        ];
-
-        Level {
-            player_spawn: (5, 5),
-            player_slime_target: 20,
-            starting_light: 20,
-            max_light: 20,
-            light_decay: 1.5,
-            message: "Quickly now!".into(),
-            walls,
-            spawners,
-            sinks: vec![],
-        }
+    /// The size of the grid for this level, or the size of the screen if it doesn't say
+    pub fn grid_size(&self) -> (u32, u32) {
+        self.size.unwrap_or(VIEW_SIZE)
     }
 }
 
 pub fn spawn_level(
     mut commands: Commands,
-    dims: Res<Dims>,
+    mut dims: ResMut<Dims>,
     colours: Res<Colors>,
     mut map: ResMut<Map>,
     mut state: ResMut<State<GameState>>,
     mut next_level: ResMut<NextLevel>,
+    playtest: Res<PlaytestLevel>,
+    level_assets: Res<LevelAssets>,
+    manifests: Res<Assets<LevelManifest>>,
+    levels: Res<Assets<Level>>,
+    player_config_files: PlayerConfigFiles,
+    mut player_config: ResMut<PlayerConfig>,
+    mut rng: ResMut<SimRng>,
+    mut ghosts: ResMut<GhostRuns>,
     mut players: Query<(
         &mut Player,
         &mut Position,
         &mut Transform,
         &mut LightingTarget,
+        &mut Hitbox,
+        &Children,
     )>,
+    mut player_sprites: Query<&mut Transform, (With<TextureAtlasSprite>, Without<Player>)>,
 ) {
-    // clear the map
+    // get the data for the next level, waiting until the files have loaded
+    let level = match &playtest.level {
+        Some(level) => Some(level.clone()),
+        None => {
+            let manifest = match manifests.get(&level_assets.manifest) {
+                Some(manifest) => manifest,
+                None => return,
+            };
+
+            match (next_level.0 as usize)
+                .checked_sub(1)
+                .and_then(|idx| manifest.levels.get(idx))
+            {
+                Some(handle) => match levels.get(handle) {
+                    Some(level) => Some(level.clone()),
+                    None => return,
+                },
+                None => None,
+            }
+        }
+    };
+
+    let default_config = match player_config_files.default_config() {
+        Some(config) => config,
+        None => return,
+    };
+
+    println!("Generating level {}", next_level.0);
+
+    // size the map for the level and clear it
+    dims.set_grid_size(level.as_ref().map_or(VIEW_SIZE, Level::grid_size));
+    map.resize(&dims);
+
     let bg = to_u8s(colours.background);
     map.clear(&dims, &bg);
 
-    // get the data for the next level
-    let level = Level::get_level(&colours, next_level.0);
-    let mut rng = thread_rng();
-
     match level {
         Some(level) => {
-            // move the player to the right spawn pos and configure them
-            for (mut player, mut pos, mut tx, mut light) in players.iter_mut() {
+            *player_config = level.player.apply(&default_config);
+
+            // play tests don't have a level number to keep a best time against
+            let level_number = if playtest.level.is_none() {
+                Some(next_level.0)
+            } else {
+                None
+            };
+            ghosts.start(level_number);
+
+            // move the player (and the camera) to the right spawn pos and configure them
+            for (mut player, mut pos, mut tx, mut light, mut hitbox, children) in players.iter_mut()
+            {
                 pos.0 = level.player_spawn.0;
                 pos.1 = level.player_spawn.1;
 
-                tx.translation = dims
-                    .grid_to_world(level.player_spawn.0, level.player_spawn.1)
-                    .extend(0.);
+                let spawn = dims.grid_to_world(level.player_spawn.0, level.player_spawn.1);
+                tx.translation = spawn.extend(0.);
+                dims.camera = dims.clamp_camera(spawn);
 
                 player.slime_target = level.player_slime_target;
 
@@ -371,29 +218,97 @@ pub fn spawn_level(
                 light.max_light_strength = level.max_light;
                 light.lighting_decay_rate = level.light_decay;
 
+                // the level may change the size of the player, keep the sprite centred over them
+                *hitbox = player_config.hitbox();
+                for &child in children.iter() {
+                    if let Ok(mut sprite_tx) = player_sprites.get_mut(child) {
+                        sprite_tx.translation = hitbox.sprite_offset(&dims);
+                    }
+                }
+
                 println!("Moved player to [{},{}] ({})", pos.0, pos.1, tx.translation);
+
+                // race against the fastest finish so far
+                if let Some(path) = level_number.and_then(|level| ghosts.best(level)) {
+                    let start = dims.grid_to_world(path[0].0, path[0].1);
+
+                    commands
+                        .spawn()
+                        .insert(Ghost { path: path.clone() })
+                        .insert(Transform::from_translation(start.extend(0.)))
+                        .insert(GlobalTransform::default())
+                        .with_children(|parent| {
+                            parent.spawn_bundle(SpriteSheetBundle {
+                                texture_atlas: ghosts.atlas.clone(),
+                                sprite: TextureAtlasSprite {
+                                    color: Color::rgba(1., 1., 1., GHOST_ALPHA),
+                                    ..Default::default()
+                                },
+                                transform: Transform::from_translation(hitbox.sprite_offset(&dims)),
+                                ..Default::default()
+                            });
+                        });
+                }
             }
 
             // spawn walls
+            let wall_colour = to_u8s(colours.walls);
             for wall in level.walls {
-                for (x, y) in wall.points {
+                for (x, y) in wall.points() {
                     let particle = Particle {
-                        pos: Vec2::new(x as f32, y as f32),
-                        vel: Vec2::ZERO,
-                        color: colours.walls.clone(),
                         particle_type: ParticleType::Obstacle,
-                        next_update: f64::MAX,
+                        vel: (0, 0),
+                        color: wall_colour,
                         is_left_first: rng.gen_bool(0.5),
+                        emissive: false,
                     };
 
-                    let entity = commands.spawn().insert(particle).insert(StaticEntity).id();
-                    map.spawn_entity(&dims, particle, entity);
+                    map.spawn(&dims, x, y, particle);
                 }
             }
 
-            // create spawners
+            // spawn any loose particles
+            for lp in level.particles {
+                let particle = Particle {
+                    particle_type: lp.particle_type,
+                    vel: (0, -1),
+                    color: to_u8s(colours.get(lp.color)),
+                    is_left_first: rng.gen_bool(0.5),
+                    emissive: lp.color.is_emissive(),
+                };
+
+                map.spawn(&dims, lp.pos.0, lp.pos.1, particle);
+            }
+
+            // create spawners, some of which glow
             for spawner in level.spawners {
-                commands.spawn().insert(spawner);
+                let mut entity = commands.spawn();
+
+                if let Some(strength) = spawner.light {
+                    entity
+                        .insert(LightEmitter {
+                            strength,
+                            color: to_u8s(colours.get(spawner.color)),
+                            falloff: DEFAULT_FALLOFF,
+                        })
+                        .insert(Position(spawner.pos.0, spawner.pos.1));
+                }
+
+                entity.insert(spawner);
+            }
+
+            // create light fixtures
+            for light in level.lights {
+                commands
+                    .spawn()
+                    .insert(LightEmitter {
+                        strength: light.strength,
+                        color: light
+                            .color
+                            .map_or([255, 255, 255], |name| to_u8s(colours.get(name))),
+                        falloff: light.falloff,
+                    })
+                    .insert(Position(light.pos.0, light.pos.1));
             }
 
             // create sinks
@@ -401,8 +316,10 @@ pub fn spawn_level(
                 commands.spawn().insert(sink);
             }
 
-            // increment the level
-            next_level.0 += 1;
+            // increment the level, play tests just repeat the same level
+            if playtest.level.is_none() {
+                next_level.0 += 1;
+            }
 
             // spawn the level message
             commands
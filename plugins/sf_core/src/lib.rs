@@ -1,16 +1,29 @@
 use bevy::prelude::*;
 use bevy_kira_audio::AudioChannel;
+use dims::Dims;
+use ghost::GhostRuns;
 use input::input_capture;
+use level_assets::{load_level_manifest, LevelLoader, LevelManifest, LevelManifestLoader};
+use levels::{Level, PlaytestLevel};
+use player_config::{load_player_config, PlayerConfig, PlayerConfigLoader};
 use render::RenderPlugin;
+use replay::{replay_input, save_recording, start_recording, stop_replay, InputLog};
+use sim::{advance_sim_clock, start_sim, SimClock, SimRng};
 
 pub mod colors;
 pub mod dims;
 pub mod entity;
 pub mod game_over_ui;
+pub mod ghost;
 pub mod input;
+pub mod level_assets;
+pub mod level_image;
 pub mod levels;
 pub mod map;
+pub mod player_config;
 pub mod render;
+pub mod replay;
+pub mod sim;
 pub mod ui;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -20,6 +33,7 @@ pub enum GameState {
     Playing,
     GameOver,
     Victory,
+    Editor,
 }
 
 pub struct MainTexture {
@@ -29,25 +43,72 @@ pub struct MainTexture {
 // marker structs
 
 pub struct MainCamera;
-pub struct StaticEntity;
 pub struct GameOver;
 pub struct TimedDespawn;
 
 pub struct Position(pub u32, pub u32);
 
+/// The size of a collision box in grid cells, with `Position` as the bottom left cell
+pub struct Hitbox {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Hitbox {
+    /// The offset from `Position` to the centre of a sprite drawn over the hitbox
+    pub fn sprite_offset(&self, dims: &Dims) -> Vec3 {
+        Vec3::new(
+            (self.width * dims.tex_stride) as f32 / 2.,
+            (self.height * dims.tex_stride) as f32 / 2.,
+            0.,
+        )
+    }
+}
+
+/// What the player is doing, used to pick their animation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PlayerState {
+    Idle,
+    Running,
+    Jumping,
+    Falling,
+    Landing,
+    WallSliding,
+    Dead,
+}
+
 pub struct Player {
     pub velocity: Vec2,
-    pub move_speed: f32,
     pub is_grounded: bool,
     pub did_jump: bool,
+    pub jump_held: bool,
+    pub last_grounded: f64,
+    pub last_jump_pressed: f64,
+
+    /// -1 when touching a wall on the left, 1 on the right and 0 otherwise
+    pub wall_side: i32,
+    pub wall_sliding: bool,
     pub jump_cooldown: isize,
 
     pub next_update: f64,
     pub frames_since_jumped: usize,
 
-    pub sink_rate: f64,
     pub slime_target: u32,
     pub next_sink: f64,
+
+    pub state: PlayerState,
+    pub state_started: f64,
+    pub facing_left: bool,
+}
+
+impl Player {
+    /// Changes state, remembering when the new state started
+    pub fn set_state(&mut self, state: PlayerState, t: f64) {
+        if self.state != state {
+            self.state = state;
+            self.state_started = t;
+        }
+    }
 }
 pub struct LightingTarget {
     pub lighting_strength: u32,
@@ -66,6 +127,36 @@ pub struct CorePlugin;
 impl Plugin for CorePlugin {
     fn build(&self, app: &mut bevy::prelude::AppBuilder) {
         app.add_plugin(RenderPlugin)
-            .add_system(input_capture.system());
+            .init_resource::<PlaytestLevel>()
+            .init_resource::<PlayerConfig>()
+            .init_resource::<SimClock>()
+            .init_resource::<SimRng>()
+            .init_resource::<InputLog>()
+            .init_resource::<GhostRuns>()
+            .add_asset::<Level>()
+            .add_asset::<LevelManifest>()
+            .add_asset::<PlayerConfig>()
+            .init_asset_loader::<LevelLoader>()
+            .init_asset_loader::<LevelManifestLoader>()
+            .init_asset_loader::<PlayerConfigLoader>()
+            .add_startup_system(load_level_manifest.system())
+            .add_startup_system(load_player_config.system())
+            .add_system(input_capture.system().label("input_capture"))
+            .add_system(
+                replay_input
+                    .system()
+                    .label("replay_input")
+                    .after("input_capture"),
+            )
+            .add_system(save_recording.system())
+            .add_system_to_stage(CoreStage::PreUpdate, advance_sim_clock.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Loading)
+                    .with_system(start_sim.system().label("start_sim"))
+                    .with_system(start_recording.system().after("start_sim")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Playing).with_system(stop_replay.system()),
+            );
     }
 }
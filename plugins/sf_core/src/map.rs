@@ -1,158 +1,165 @@
-use bevy::prelude::{Entity, Res, Texture};
+use std::ops::Range;
 
-use crate::{colors::to_u8s, dims::Dims, entity::Particle};
+use bevy::{
+    prelude::Texture,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+use sf_sim::{Grid, Simulation};
 
+use crate::{
+    dims::Dims,
+    entity::{Particle, ParticleType},
+};
+
+/// The texture alpha holds how much a particle blocks light, from 0 to `OPAQUE`,
+/// with the top bit set for particles that glow. See `opacity` in the shaders.
+const OPAQUE: u8 = 127;
+const EMISSIVE: u8 = 128;
+
+/// How much a grain of sand blocks light, walls block all of it
+const SAND_OPACITY: u8 = 32;
+
+/// Owns the particle data for every cell in the level, along with the
+/// texture that is used to display it.
 pub struct Map {
     w: u32,
     h: u32,
-    pub map: Vec<Option<Entity>>,
+    pub sim: Simulation,
+    /// only change this through the methods below, so the changes are copied to the main texture
     pub raw_texture: Texture,
+    /// texture rows changed since the last copy, top row first
+    dirty_rows: Vec<bool>,
+    is_dirty: bool,
 }
 
 impl Map {
-    pub fn new(dims: Dims, raw_texture: Texture) -> Self {
+    pub fn new(dims: &Dims) -> Self {
         Map {
-            map: vec![None; (dims.tex_w * dims.tex_h) as usize],
-            raw_texture,
+            sim: Simulation::new(dims.tex_w, dims.tex_h),
+            raw_texture: blank_texture(dims),
             w: dims.tex_w,
             h: dims.tex_h,
+            dirty_rows: vec![true; dims.tex_h as usize],
+            is_dirty: true,
         }
     }
 
-    /// Returns a grid around the given cell, which is true if the grid is unoccupied, false otherwise
-    /// numbering is [top left, top mid, top right, mid left, mid mid, mid right, bottom left, bottom mid, bottom right]
-    pub fn test_free_neighbours(&mut self, x: u32, y: u32) -> [bool; 9] {
-        [
-            if x > 0 && y < self.h - 1 {
-                self.get(x - 1, y + 1).is_none()
-            } else {
-                false
-            },
-            //
-            if y < self.h - 1 {
-                self.get(x, y + 1).is_none()
-            } else {
-                false
-            },
-            //
-            if x < self.w - 1 && y < self.h - 1 {
-                self.get(x + 1, y + 1).is_none()
-            } else {
-                false
-            },
-            //
-            if x > 0 {
-                self.get(x - 1, y).is_none()
-            } else {
-                false
-            },
-            //
-            false,
-            //
-            if x < self.w - 1 {
-                self.get(x + 1, y).is_none()
-            } else {
-                false
-            },
-            //
-            if x > 0 && y > 0 {
-                self.get(x - 1, y - 1).is_none()
-            } else {
-                false
-            },
-            //
-            if y > 0 {
-                self.get(x, y - 1).is_none()
-            } else {
-                false
-            },
-            //
-            if y > 0 && x < self.w - 1 {
-                self.get(x + 1, y - 1).is_none()
-            } else {
-                false
-            },
-        ]
+    /// Starts again with an empty grid if the size in `dims` has changed
+    pub fn resize(&mut self, dims: &Dims) {
+        if (self.w, self.h) != (dims.tex_w, dims.tex_h) {
+            println!("Resizing the grid to {}x{}", dims.tex_w, dims.tex_h);
+            *self = Map::new(dims);
+        }
     }
 
-    pub fn get(&mut self, x: u32, y: u32) -> Option<Entity> {
-        if y >= self.h || x >= self.w {
-            // safety checks failed
-            return None;
+    /// returns the ranges of texture data changed since the last call, merging
+    /// neighbouring rows. Empty if nothing has changed.
+    pub fn take_dirty_ranges(&mut self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        if !self.is_dirty {
+            return ranges;
+        }
+
+        let row_bytes = self.w as usize * 4;
+        for (row, dirty) in self.dirty_rows.iter_mut().enumerate() {
+            if !*dirty {
+                continue;
+            }
+
+            *dirty = false;
+            let start = row * row_bytes;
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = start + row_bytes,
+                _ => ranges.push(start..start + row_bytes),
+            }
         }
-        let grid_idx = self.to_grid(x, y);
-        self.map[grid_idx]
+
+        self.is_dirty = false;
+        ranges
+    }
+
+    /// flags the texture row holding grid row `y` to be copied
+    fn mark_dirty(&mut self, y: u32) {
+        self.dirty_rows[(self.h - y - 1) as usize] = true;
+        self.is_dirty = true;
     }
 
-    /// spawns a new entity at the given position
-    pub fn spawn_entity(&mut self, dims: &Res<Dims>, particle: Particle, entity: Entity) {
-        let x = particle.pos.x.floor() as u32;
-        let y = particle.pos.y.floor() as u32;
-        let idx = self.to_grid(x, y);
-        let cols = to_u8s(particle.color);
+    pub fn get(&self, x: u32, y: u32) -> Option<&Particle> {
+        self.sim.get(x, y)
+    }
 
-        // add to the map
-        self.map[idx] = Some(entity);
+    /// spawns a new particle at the given position, returns false if the cell is occupied
+    pub fn spawn(&mut self, dims: &Dims, x: u32, y: u32, particle: Particle) -> bool {
+        if !self.sim.spawn(x, y, particle) {
+            return false;
+        }
 
         // add to the texture
-        self.set_pixel(dims, x, y, cols);
+        self.set_pixel(dims, x, y, particle.color);
+        self.set_alpha(dims, x, y, light_flags(&particle));
+        true
     }
 
     /// Sets RGB (NOT ALPHA!!) on the given
-    pub fn set_pixel(&mut self, dims: &Res<Dims>, x: u32, y: u32, col: [u8; 3]) {
+    pub fn set_pixel(&mut self, dims: &Dims, x: u32, y: u32, col: [u8; 3]) {
         for (pixel, idx) in dims.to_range_enumerate(x, y) {
             // set rgb
             self.raw_texture.data[idx] = col[pixel];
         }
+        self.mark_dirty(y);
     }
 
-    /// gets the current alpha channel value at the given location
-    pub fn get_alpha(&mut self, dims: &Res<Dims>, x: u32, y: u32) -> u8 {
+    /// gets the current alpha channel value at the given location, which holds
+    /// how much the particle there blocks light
+    pub fn get_alpha(&mut self, dims: &Dims, x: u32, y: u32) -> u8 {
         let alpha_channel = dims.to_alpha_index(x, y);
         self.raw_texture.data[alpha_channel]
     }
 
     /// sets the alpha channel
-    pub fn set_alpha(&mut self, dims: &Res<Dims>, x: u32, y: u32, alpha: u8) {
+    pub fn set_alpha(&mut self, dims: &Dims, x: u32, y: u32, alpha: u8) {
         let alpha_channel = dims.to_alpha_index(x, y);
         self.raw_texture.data[alpha_channel] = alpha;
+        self.mark_dirty(y);
+    }
+
+    /// steps the particle simulation once, updating the texture for anything that moved
+    pub fn step(&mut self, dims: &Dims, empty_colour: [u8; 3]) {
+        for mv in self.sim.step() {
+            if mv.displaced {
+                self.swap_pixels(dims, mv.from, mv.to);
+            } else {
+                self.move_pixel(dims, mv.from, mv.to, empty_colour);
+            }
+        }
     }
 
-    /// moves an entity to a new position, swapping the colours
-    pub fn move_entity(
+    /// moves a particle to a new (empty) position, swapping the colours.
+    /// Returns false if there was nothing to move or the new position was taken
+    pub fn move_particle(
         &mut self,
-        dims: &Res<Dims>,
+        dims: &Dims,
         prev: (u32, u32),
         next: (u32, u32),
         empty_colour: [u8; 3],
-    ) {
-        // update the entity mapping
-        let old_idx = self.to_grid(prev.0, prev.1);
-        let entity = self.map[old_idx];
-        self.map[old_idx] = None;
-
-        let idx = self.to_grid(next.0, next.1);
-        self.map[idx] = entity;
-
-        // update the texture, first set the old field to empty
-        let old_pos = dims.to_range(prev.0, prev.1);
-        let new_pos = dims.to_range(next.0, next.1);
-
-        old_pos.zip(new_pos).for_each(|(o, n)| {
-            self.raw_texture.data.swap(o, n);
-        });
+    ) -> bool {
+        if !self.sim.is_free(next.0 as i32, next.1 as i32) {
+            return false;
+        }
 
-        for (pixel, idx) in dims.to_range_enumerate(prev.0, prev.1) {
-            self.raw_texture.data[idx] = empty_colour[pixel];
+        match self.sim.remove(prev.0, prev.1) {
+            Some(particle) => {
+                self.sim.spawn(next.0, next.1, particle);
+                self.move_pixel(dims, prev, next, empty_colour);
+                true
+            }
+            None => false,
         }
     }
 
-    pub fn clear(&mut self, dims: &Res<Dims>, clear_colour: &[u8; 3]) {
-        // remove all entities
-        let count = self.map.len();
-        for n in 0..count {
-            self.map[n] = None;
-        }
+    pub fn clear(&mut self, dims: &Dims, clear_colour: &[u8; 3]) {
+        // remove all particles
+        self.sim.clear();
 
         // set texture to the background colour
         for x in 0..dims.tex_w {
@@ -167,18 +174,109 @@ impl Map {
                 self.raw_texture.data[last_idx + 1] = 0;
             }
         }
+
+        self.dirty_rows.iter_mut().for_each(|dirty| *dirty = true);
+        self.is_dirty = true;
     }
 
-    pub fn destroy_at(&mut self, x: u32, y: u32, dims: &Res<Dims>, clear_colour: &[u8; 3]) {
-        let grid_idx = self.to_grid(x, y);
-        self.map[grid_idx] = None;
+    /// removes the particle at the given location, returning it if there was one
+    pub fn destroy_at(
+        &mut self,
+        x: u32,
+        y: u32,
+        dims: &Dims,
+        clear_colour: &[u8; 3],
+    ) -> Option<Particle> {
+        let particle = self.sim.remove(x, y);
 
         for (pixel, idx) in dims.to_range_enumerate(x, y) {
             self.raw_texture.data[idx] = clear_colour[pixel];
         }
+        self.set_alpha(dims, x, y, 0);
+
+        particle
+    }
+
+    /// update the texture, first swap the colours then set the old field to empty
+    fn move_pixel(
+        &mut self,
+        dims: &Dims,
+        prev: (u32, u32),
+        next: (u32, u32),
+        empty_colour: [u8; 3],
+    ) {
+        let old_pos = dims.to_range(prev.0, prev.1);
+        let new_pos = dims.to_range(next.0, next.1);
+
+        old_pos.zip(new_pos).for_each(|(o, n)| {
+            self.raw_texture.data.swap(o, n);
+        });
+
+        for (pixel, idx) in dims.to_range_enumerate(prev.0, prev.1) {
+            self.raw_texture.data[idx] = empty_colour[pixel];
+        }
+
+        // the opacity moves with the particle
+        let alpha = self.get_alpha(dims, prev.0, prev.1);
+        self.set_alpha(dims, next.0, next.1, alpha);
+        self.set_alpha(dims, prev.0, prev.1, 0);
+    }
+
+    /// update the texture for two particles that swapped places, colour and opacity included
+    fn swap_pixels(&mut self, dims: &Dims, a: (u32, u32), b: (u32, u32)) {
+        let a_pos = dims.to_range(a.0, a.1);
+        let b_pos = dims.to_range(b.0, b.1);
+
+        a_pos.zip(b_pos).for_each(|(a, b)| {
+            self.raw_texture.data.swap(a, b);
+        });
+
+        let a_alpha = self.get_alpha(dims, a.0, a.1);
+        let b_alpha = self.get_alpha(dims, b.0, b.1);
+        self.set_alpha(dims, a.0, a.1, b_alpha);
+        self.set_alpha(dims, b.0, b.1, a_alpha);
+    }
+}
+
+/// A fully transparent texture the size of the grid
+fn blank_texture(dims: &Dims) -> Texture {
+    Texture::new(
+        Extent3d::new(dims.tex_w, dims.tex_h, 1),
+        TextureDimension::D2,
+        vec![0; dims.texture_values()],
+        TextureFormat::Rgba8Unorm,
+    )
+}
+
+/// How the particle affects lighting, stored in the texture alpha for the shader
+fn light_flags(particle: &Particle) -> u8 {
+    let opacity = match particle.particle_type {
+        ParticleType::Obstacle => OPAQUE,
+        ParticleType::Sand => SAND_OPACITY,
+        _ => 0,
+    };
+
+    if particle.emissive {
+        opacity | EMISSIVE
+    } else {
+        opacity
+    }
+}
+
+impl Grid for Map {
+    fn width(&self) -> u32 {
+        self.w
+    }
+
+    fn height(&self) -> u32 {
+        self.h
+    }
+
+    fn particle_at(&self, x: i32, y: i32) -> Option<&Particle> {
+        self.sim.particle_at(x, y)
     }
 
-    fn to_grid(&self, x: u32, y: u32) -> usize {
-        ((self.h - y - 1) * self.w + x) as usize
+    fn is_free(&self, x: i32, y: i32) -> bool {
+        self.sim.is_free(x, y)
     }
 }
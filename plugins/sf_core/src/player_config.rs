@@ -0,0 +1,238 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadState, LoadedAsset},
+    ecs::system::SystemParam,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::Hitbox;
+
+/// The file holding the default player physics, levels can override parts of it
+pub const PLAYER_CONFIG: &str = "default.player";
+
+/// Tuning for how the player moves and collects slime. The active config is
+/// stored as a resource and replaced by `spawn_level` at the start of each level.
+/// Anything missing from the file uses the built in default.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
+#[uuid = "266ce327-5992-4263-a03b-d38805b9f647"]
+#[serde(default)]
+pub struct PlayerConfig {
+    /// the maximum horizontal speed
+    pub move_speed: f32,
+    /// horizontal speed gained per update
+    pub acceleration: f32,
+    /// vertical speed lost per update while in the air
+    pub gravity: f32,
+    /// the vertical speed of a jump
+    pub jump_size: f32,
+    /// multiplies the upward speed when jump is released early, giving shorter jumps
+    pub jump_release_ratio: f32,
+    /// seconds after walking off a ledge that the player can still jump
+    pub coyote_time: f64,
+    /// seconds before landing that a jump press is remembered for
+    pub jump_buffer: f64,
+    /// the fastest the player can fall (negative is down)
+    pub terminal_fall_velocity: f32,
+    /// the fastest the player can fall while sliding down a wall
+    pub wall_slide_velocity: f32,
+    /// the horizontal speed of a jump off a wall, limited by `move_speed`
+    pub wall_jump_push: f32,
+    /// multiplies horizontal acceleration while in the air
+    pub air_speed_ratio: f32,
+    /// multiplies the top speed when the hitbox is full of sand
+    pub sand_speed_ratio: f32,
+    /// multiplies gravity when the hitbox is full of liquid
+    pub liquid_gravity_ratio: f32,
+    /// upward speed gained per update when the hitbox is full of liquid
+    pub buoyancy: f32,
+    /// extra upward speed per update from holding jump in liquid
+    pub swim_strength: f32,
+    /// fraction of speed lost per update when the hitbox is full of liquid
+    pub liquid_drag: f32,
+    /// how many filled cells sand needs beneath it before it can be stood on
+    pub packed_sand_depth: u32,
+    /// seconds between player movement updates
+    pub update_rate: f32,
+    /// seconds between collecting particles
+    pub sink_rate: f64,
+    /// the width of the player's collision box in cells
+    pub hitbox_width: u32,
+    /// the height of the player's collision box in cells
+    pub hitbox_height: u32,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig {
+            move_speed: 200.,
+            acceleration: 7.5,
+            gravity: 18.75,
+            jump_size: 400.,
+            jump_release_ratio: 0.5,
+            coyote_time: 0.1,
+            jump_buffer: 0.1,
+            terminal_fall_velocity: -450.,
+            wall_slide_velocity: -100.,
+            wall_jump_push: 200.,
+            air_speed_ratio: 2.,
+            sand_speed_ratio: 0.4,
+            liquid_gravity_ratio: 0.3,
+            buoyancy: 8.,
+            swim_strength: 12.,
+            liquid_drag: 0.05,
+            packed_sand_depth: 2,
+            update_rate: 1. / 60.,
+            sink_rate: 1. / 10., // 10 per second
+            hitbox_width: 2,
+            hitbox_height: 4,
+        }
+    }
+}
+
+impl PlayerConfig {
+    /// The player's collision box, always at least one cell in each direction
+    pub fn hitbox(&self) -> Hitbox {
+        Hitbox {
+            width: self.hitbox_width.max(1),
+            height: self.hitbox_height.max(1),
+        }
+    }
+}
+
+/// Per level changes to the player config, anything left out uses the default
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlayerConfigOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub move_speed: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acceleration: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gravity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jump_size: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jump_release_ratio: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coyote_time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jump_buffer: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terminal_fall_velocity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_slide_velocity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_jump_push: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub air_speed_ratio: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sand_speed_ratio: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liquid_gravity_ratio: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buoyancy: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swim_strength: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liquid_drag: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packed_sand_depth: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_rate: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sink_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hitbox_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hitbox_height: Option<u32>,
+}
+
+impl PlayerConfigOverrides {
+    pub fn apply(&self, base: &PlayerConfig) -> PlayerConfig {
+        PlayerConfig {
+            move_speed: self.move_speed.unwrap_or(base.move_speed),
+            acceleration: self.acceleration.unwrap_or(base.acceleration),
+            gravity: self.gravity.unwrap_or(base.gravity),
+            jump_size: self.jump_size.unwrap_or(base.jump_size),
+            jump_release_ratio: self.jump_release_ratio.unwrap_or(base.jump_release_ratio),
+            coyote_time: self.coyote_time.unwrap_or(base.coyote_time),
+            jump_buffer: self.jump_buffer.unwrap_or(base.jump_buffer),
+            terminal_fall_velocity: self
+                .terminal_fall_velocity
+                .unwrap_or(base.terminal_fall_velocity),
+            wall_slide_velocity: self.wall_slide_velocity.unwrap_or(base.wall_slide_velocity),
+            wall_jump_push: self.wall_jump_push.unwrap_or(base.wall_jump_push),
+            air_speed_ratio: self.air_speed_ratio.unwrap_or(base.air_speed_ratio),
+            sand_speed_ratio: self.sand_speed_ratio.unwrap_or(base.sand_speed_ratio),
+            liquid_gravity_ratio: self
+                .liquid_gravity_ratio
+                .unwrap_or(base.liquid_gravity_ratio),
+            buoyancy: self.buoyancy.unwrap_or(base.buoyancy),
+            swim_strength: self.swim_strength.unwrap_or(base.swim_strength),
+            liquid_drag: self.liquid_drag.unwrap_or(base.liquid_drag),
+            packed_sand_depth: self.packed_sand_depth.unwrap_or(base.packed_sand_depth),
+            update_rate: self.update_rate.unwrap_or(base.update_rate),
+            sink_rate: self.sink_rate.unwrap_or(base.sink_rate),
+            hitbox_width: self.hitbox_width.unwrap_or(base.hitbox_width),
+            hitbox_height: self.hitbox_height.unwrap_or(base.hitbox_height),
+        }
+    }
+}
+
+/// Holds the handle to the default player config loaded from `PLAYER_CONFIG`
+pub struct PlayerConfigAssets {
+    pub default: Handle<PlayerConfig>,
+}
+
+/// Everything needed to look up the default player config in a system
+#[derive(SystemParam)]
+pub struct PlayerConfigFiles<'a> {
+    asset_server: Res<'a, AssetServer>,
+    assets: Res<'a, PlayerConfigAssets>,
+    configs: Res<'a, Assets<PlayerConfig>>,
+}
+
+impl<'a> PlayerConfigFiles<'a> {
+    /// The default player config, falling back to the built in values if the file
+    /// is broken. Returns None while the file is still loading.
+    pub fn default_config(&self) -> Option<PlayerConfig> {
+        match self.asset_server.get_load_state(&self.assets.default) {
+            LoadState::Loaded => Some(
+                self.configs
+                    .get(&self.assets.default)
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+            LoadState::Failed => Some(PlayerConfig::default()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PlayerConfigLoader;
+
+impl AssetLoader for PlayerConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let config: PlayerConfig = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["player"]
+    }
+}
+
+pub fn load_player_config(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(PlayerConfigAssets {
+        default: asset_server.load(PLAYER_CONFIG),
+    });
+}
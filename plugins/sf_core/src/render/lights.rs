@@ -0,0 +1,84 @@
+//! Lights are passed to the world shader in a small texture with one column per light.
+//! Values are stored as 16 bit fixed point numbers split over two 8 bit channels
+//! so the same texture works on native and WebGL2.
+
+use bevy::{
+    prelude::*,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+
+/// The rows of the light texture: position, strength and falloff, then colour
+pub const LIGHT_ROWS: usize = 3;
+
+/// Light fades with the square of the distance to its edge
+pub const DEFAULT_FALLOFF: f32 = 2.;
+
+/// Positions and strengths are stored in 1/16ths of a cell
+const FIXED_POINT_SCALE: f32 = 16.;
+
+pub struct LightingSettings {
+    /// the most lights that will be drawn at once, extra lights are dropped
+    pub max_lights: usize,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        LightingSettings { max_lights: 16 }
+    }
+}
+
+/// A light ready to be drawn, in texture coordinates (y pointing down the texture)
+#[derive(Debug, Copy, Clone)]
+pub struct PointLight {
+    pub pos: Vec2,
+    pub strength: f32,
+    pub color: [u8; 3],
+    /// the light at a fraction `f` of the way to the edge is `1 - f^falloff`
+    pub falloff: f32,
+}
+
+/// Creates a texture with room for `max_lights` lights
+pub fn light_texture(max_lights: usize) -> Texture {
+    Texture::new(
+        Extent3d::new(max_lights as u32, LIGHT_ROWS as u32, 1),
+        TextureDimension::D2,
+        vec![0; max_lights * LIGHT_ROWS * 4],
+        TextureFormat::Rgba8Unorm,
+    )
+}
+
+/// Writes the lights into the texture, returning how many fit
+pub fn write_lights(texture: &mut Texture, lights: &[PointLight]) -> usize {
+    let width = texture.size.width as usize;
+    let count = lights.len().min(width);
+
+    for (x, light) in lights.iter().take(count).enumerate() {
+        set_texel(texture, (x, 0), [encode(light.pos.x), encode(light.pos.y)]);
+        set_texel(
+            texture,
+            (x, 1),
+            [encode(light.strength), encode(light.falloff)],
+        );
+        set_texel(
+            texture,
+            (x, 2),
+            [[light.color[0], light.color[1]], [light.color[2], 255]],
+        );
+    }
+
+    count
+}
+
+fn set_texel(texture: &mut Texture, pos: (usize, usize), values: [[u8; 2]; 2]) {
+    let idx = (pos.1 * texture.size.width as usize + pos.0) * 4;
+    texture.data[idx..idx + 2].copy_from_slice(&values[0]);
+    texture.data[idx + 2..idx + 4].copy_from_slice(&values[1]);
+}
+
+/// Splits a fixed point value into high and low bytes, see `decode` in the shaders
+fn encode(value: f32) -> [u8; 2] {
+    ((value * FIXED_POINT_SCALE)
+        .round()
+        .clamp(0., u16::MAX as f32) as u16)
+        .to_be_bytes()
+}
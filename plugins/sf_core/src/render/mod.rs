@@ -1,7 +1,8 @@
 use bevy::prelude::*;
 
-use self::render_pipeline::LightSource;
+use self::{lights::LightingSettings, render_pipeline::LightSource};
 
+pub mod lights;
 pub mod render_pipeline;
 
 #[cfg(target_arch = "wasm32")]
@@ -9,13 +10,16 @@ pub mod render_pipeline_web;
 
 pub mod render_texture;
 pub mod setup_rendering;
+pub mod update_camera;
 
 pub struct RenderPlugin;
 
 impl Plugin for RenderPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_system(render_texture::render_texture.system())
+            .add_system(update_camera::update_camera.system().label("update_camera"))
             .add_asset::<LightSource>()
+            .init_resource::<LightingSettings>()
             .add_startup_system(setup_rendering::setup_rendering.system());
     }
 }
@@ -9,9 +9,13 @@ use bevy::{
 #[derive(RenderResources, Default, TypeUuid)]
 #[uuid = "ddedb840-80d2-4c53-93b4-6cc6f642684b"]
 pub struct LightSource {
-    pub pos: Vec2,
     pub dims: Vec2,
-    pub strength: f32,
+    /// the light level everywhere, 1.0 lights the whole level
+    pub ambient: f32,
+    /// the number of lights written to `lights`
+    pub light_count: f32,
+    /// see `lights::write_lights`
+    pub lights: Handle<Texture>,
 }
 
 pub fn get_custom_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
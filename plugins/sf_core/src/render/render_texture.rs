@@ -1,12 +1,37 @@
 use bevy::prelude::*;
 
-use crate::{map::Map, MainTexture};
+use super::render_pipeline::LightSource;
+use crate::{dims::Dims, map::Map, MainTexture};
 
+/// Copies the rows of the map that changed into the main texture. Nothing is
+/// touched when the map hasn't changed, so the texture isn't sent to the GPU again.
+/// Otherwise bevy still re-uploads the whole texture, only the CPU side copy is limited
+/// to the changed rows. When the grid changes size the whole texture is replaced and
+/// the world sprite resized.
 pub fn render_texture(
-    main_texture: ResMut<MainTexture>,
+    main_texture: Res<MainTexture>,
     mut textures: ResMut<Assets<Texture>>,
-    map: Res<Map>,
+    mut map: ResMut<Map>,
+    dims: Res<Dims>,
+    mut world: Query<(&mut Sprite, &mut LightSource)>,
 ) {
+    let ranges = map.take_dirty_ranges();
+    if ranges.is_empty() {
+        return;
+    }
+
     let texture = textures.get_mut(main_texture.texture.clone()).unwrap();
-    texture.data.copy_from_slice(&map.raw_texture.data);
+    if texture.size != map.raw_texture.size {
+        *texture = map.raw_texture.clone();
+
+        for (mut sprite, mut light) in world.iter_mut() {
+            sprite.size = Vec2::new(dims.world_w(), dims.world_h());
+            light.dims = Vec2::new(dims.tex_w as f32, dims.tex_h as f32);
+        }
+        return;
+    }
+
+    for range in ranges {
+        texture.data[range.clone()].copy_from_slice(&map.raw_texture.data[range]);
+    }
 }
@@ -16,15 +16,73 @@ uniform sampler2D ColorMaterial_texture;  // set = 1, binding = 1
 
 
 // light source
-uniform LightSource_pos { // set = 3, binding = 0
-    vec2 light_pos;
-};
-uniform LightSource_dims { // set = 3, binding = 1
+uniform LightSource_dims { // set = 3, binding = 0
     vec2 grid_dims;
 };
-uniform LightSource_strength { // set = 3, binding = 2
-    float light_strength;
+uniform LightSource_ambient { // set = 3, binding = 1
+    float ambient;
+};
+uniform LightSource_light_count { // set = 3, binding = 2
+    float light_count;
 };
+uniform sampler2D LightSource_lights; // set = 3, binding = 3
+
+// lights are stored as 16 bit fixed point values split over two channels, see lights.rs
+float decode(vec2 bytes) {
+    return (floor(bytes.x * 255.0 + 0.5) * 256.0 + floor(bytes.y * 255.0 + 0.5)) / 16.0;
+}
+
+vec4 light_texel(int light, int row) {
+    return texelFetch(LightSource_lights, ivec2(light, row), 0);
+}
+
+# ifdef COLORMATERIAL_TEXTURE
+// how far glowing particles light their surroundings, in cells, and how brightly
+const int GLOW_RADIUS = 3;
+const float GLOW_STRENGTH = 0.35;
+
+vec4 world_texel(ivec2 cell) {
+    return texelFetch(ColorMaterial_texture, cell, 0);
+}
+
+// the texture alpha holds how much each cell blocks light (0 to 127, walls block all
+// of it), with the top bit set for cells that glow. See map.rs
+float alpha_bits(vec4 texel) {
+    return floor(texel.a * 255.0 + 0.5);
+}
+
+float opacity(vec4 texel) {
+    return mod(alpha_bits(texel), 128.0) / 127.0;
+}
+
+bool is_emissive(vec4 texel) {
+    return alpha_bits(texel) >= 128.0;
+}
+
+// scales a colour so the brightest channel is 1, so coloured light tints without darkening
+vec3 hue(vec3 c) {
+    return c / max(max(c.r, c.g), max(c.b, 0.001));
+}
+
+// how much light makes it between two points, stepping once per row or column
+// crossed. The cells at either end are skipped so lit walls still show up.
+float transmittance(vec2 from, vec2 to) {
+    ivec2 from_cell = ivec2(floor(from));
+    ivec2 to_cell = ivec2(floor(to));
+    vec2 d = to - from;
+    int steps = int(ceil(max(abs(d.x), abs(d.y))));
+
+    float t = 1.0;
+    for (int s = 1; s < steps && t > 0.0; s++) {
+        ivec2 cell = ivec2(floor(from + d * (float(s) / float(steps))));
+        if (cell != from_cell && cell != to_cell) {
+            t *= 1.0 - opacity(world_texel(cell));
+        }
+    }
+
+    return t;
+}
+# endif
 
 void main() {
     vec4 color = Color;
@@ -34,18 +92,57 @@ void main() {
         v_Uv
     );
 
-    // now calculate the correct alpha based on the distance to the light source
+    // now calculate the correct alpha based on the distance to each light source
     // need to do this in grid coordinates, not uv coordinates as these would result in
     // a non-circular light
     // 
-    // first calculate the grid coordinates of the UVs. Pos should be in grid coords already
-    float grid_x = grid_dims.x * v_Uv.x;
-    float grid_y = grid_dims.y * v_Uv.y;
+    // first calculate the grid coordinates of the UVs. Light positions are in grid coords already
+    vec2 grid_pos = grid_dims * v_Uv;
+    ivec2 grid_cell = ivec2(floor(grid_pos));
+
+    // the total light, and the light colour weighted by how much each light adds
+    float light = ambient;
+    vec3 tint = vec3(ambient);
+
+    for (int i = 0; i < int(light_count); i++) {
+        vec4 pos = light_texel(i, 0);
+        vec4 params = light_texel(i, 1);
+        float strength = decode(params.rg);
+        if (strength <= 0.0) {
+            continue;
+        }
+
+        vec2 light_pos = vec2(decode(pos.rg), decode(pos.ba));
+        float falloff = 1.0 - clamp(pow(length(grid_pos - light_pos) / strength, decode(params.ba)), 0.0, 1.0);
+        if (falloff > 0.0) {
+            float contribution = falloff * transmittance(grid_pos, light_pos);
+            light += contribution;
+            tint += contribution * hue(light_texel(i, 2).rgb);
+        }
+    }
+
+    // glowing particles light the cells around them in their own colour
+    for (int gy = -GLOW_RADIUS; gy <= GLOW_RADIUS; gy++) {
+        for (int gx = -GLOW_RADIUS; gx <= GLOW_RADIUS; gx++) {
+            ivec2 cell = grid_cell + ivec2(gx, gy);
+            if (any(lessThan(cell, ivec2(0))) || any(greaterThanEqual(cell, ivec2(grid_dims)))) {
+                continue;
+            }
+
+            vec4 texel = world_texel(cell);
+            if (!is_emissive(texel)) {
+                continue;
+            }
 
-    float dx = grid_x - light_pos.x;
-    float dy = grid_y - light_pos.y;
+            float glow = 1.0 - clamp(length(vec2(cell) + 0.5 - grid_pos) / float(GLOW_RADIUS + 1), 0.0, 1.0);
+            glow *= glow * GLOW_STRENGTH;
+            light += glow;
+            tint += glow * hue(texel.rgb);
+        }
+    }
 
-    color.a = 1.0 - clamp((dx * dx + dy * dy) / (light_strength * light_strength), 0.0, 1.0);
+    color.rgb *= tint / max(light, 0.0001);
+    color.a = clamp(light, 0.0, 1.0);
 #endif
 
     o_Target = color;
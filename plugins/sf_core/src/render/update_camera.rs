@@ -0,0 +1,33 @@
+use bevy::{prelude::*, render::camera::OrthographicProjection};
+
+use crate::{dims::Dims, MainCamera};
+
+/// Scales the main camera so the view fits in the window, whatever size it is, and
+/// moves it to `Dims::camera`. Runs every frame so it also picks up the starting size
+/// of the window or browser tab.
+pub fn update_camera(
+    windows: Res<Windows>,
+    mut dims: ResMut<Dims>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    if let Some(window) = windows.get_primary() {
+        if (window.width() - dims.win_w).abs() > f32::EPSILON
+            || (window.height() - dims.win_h).abs() > f32::EPSILON
+        {
+            dims.fit_window(window.width(), window.height());
+        }
+    }
+
+    if !dims.is_changed() {
+        return;
+    }
+
+    for (mut tx, mut projection) in cameras.iter_mut() {
+        tx.translation.x = dims.camera.x;
+        tx.translation.y = dims.camera.y;
+
+        if (projection.scale - 1. / dims.zoom).abs() > f32::EPSILON {
+            projection.scale = 1. / dims.zoom;
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Records the input for each gameplay tick so a level can be played back exactly.
+//! Along with the level number and seed this is all that's needed to repeat a run,
+//! see `sim`. Press F8 while playing to save the current level's recording.
+//!
+//! Recordings are saved as a small binary file: a header followed by runs of
+//! identical ticks, as most ticks have the same input as the one before.
+
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+
+use crate::{
+    input::InputState,
+    levels::NextLevel,
+    sim::{SimClock, SimRng},
+};
+
+const MAGIC: &[u8; 4] = b"SFR1";
+const HEADER_LEN: usize = 16;
+const RUN_LEN: usize = 7;
+
+const LEFT: u8 = 1;
+const RIGHT: u8 = 2;
+const JUMP: u8 = 4;
+const MOUSE: u8 = 8;
+
+/// Where F8 saves recordings, and where the best ghost runs are kept
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const REPLAY_DIR: &str = "replays";
+
+/// The input for a single tick. The cursor is only kept to the nearest cell.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct TickInput {
+    flags: u8,
+    cursor: (u16, u16),
+}
+
+impl TickInput {
+    pub fn from_input(input: &InputState) -> Self {
+        let flag = |pressed: bool, flag: u8| if pressed { flag } else { 0 };
+        let cell = |pos: f32| pos.floor().max(0.).min(u16::MAX as f32) as u16;
+
+        TickInput {
+            flags: flag(input.left_pressed, LEFT)
+                | flag(input.right_pressed, RIGHT)
+                | flag(input.jump_pressed, JUMP)
+                | flag(input.mouse_down, MOUSE),
+            cursor: (cell(input.cursor_pos.x), cell(input.cursor_pos.y)),
+        }
+    }
+
+    pub fn apply(&self, input: &mut InputState) {
+        input.left_pressed = self.flags & LEFT != 0;
+        input.right_pressed = self.flags & RIGHT != 0;
+        input.jump_pressed = self.flags & JUMP != 0;
+        input.mouse_down = self.flags & MOUSE != 0;
+        input.cursor_pos = Vec2::new(self.cursor.0 as f32 + 0.5, self.cursor.1 as f32 + 0.5);
+    }
+}
+
+/// The inputs for one attempt at a level
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    /// the level number, as in `NextLevel`
+    pub level: u32,
+    pub seed: u64,
+    pub ticks: Vec<TickInput>,
+}
+
+impl Recording {
+    pub fn new(level: u32, seed: u64) -> Self {
+        Recording {
+            level,
+            seed,
+            ticks: vec![],
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.level.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+
+        let mut ticks = self.ticks.iter().peekable();
+        while let Some(tick) = ticks.next() {
+            let mut count: u16 = 1;
+            while count < u16::MAX && ticks.peek() == Some(&tick) {
+                ticks.next();
+                count += 1;
+            }
+
+            bytes.push(tick.flags);
+            bytes.extend_from_slice(&tick.cursor.0.to_le_bytes());
+            bytes.extend_from_slice(&tick.cursor.1.to_le_bytes());
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            bail!("not a replay file");
+        }
+
+        let runs = bytes[HEADER_LEN..].chunks_exact(RUN_LEN);
+        if !runs.remainder().is_empty() {
+            bail!("replay file is truncated");
+        }
+
+        let mut u32_bytes = [0; 4];
+        let mut u64_bytes = [0; 8];
+        u32_bytes.copy_from_slice(&bytes[4..8]);
+        u64_bytes.copy_from_slice(&bytes[8..16]);
+
+        let mut recording =
+            Recording::new(u32::from_le_bytes(u32_bytes), u64::from_le_bytes(u64_bytes));
+
+        for run in runs {
+            let u16_at = |idx: usize| u16::from_le_bytes([run[idx], run[idx + 1]]);
+            let tick = TickInput {
+                flags: run[0],
+                cursor: (u16_at(1), u16_at(3)),
+            };
+
+            let count = u16_at(5) as usize;
+            if count == 0 {
+                bail!("replay file has an empty run of ticks");
+            }
+
+            let len = recording.ticks.len();
+            recording.ticks.resize(len + count, tick);
+        }
+
+        Ok(recording)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self> {
+        Recording::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+/// The recording of the level being played, and a recording to play back in place
+/// of the keyboard if there is one
+#[derive(Default)]
+pub struct InputLog {
+    pub recording: Recording,
+    replay: Option<Recording>,
+    replaying: bool,
+}
+
+impl InputLog {
+    /// Plays back the recording when the next level is loaded
+    pub fn replay(recording: Recording) -> Self {
+        InputLog {
+            replay: Some(recording),
+            ..Default::default()
+        }
+    }
+}
+
+/// Starts a new recording as a level is loaded. If a replay is waiting this
+/// switches to the recorded level and seed first.
+pub fn start_recording(
+    mut log: ResMut<InputLog>,
+    mut next_level: ResMut<NextLevel>,
+    mut rng: ResMut<SimRng>,
+) {
+    let pending = match &log.replay {
+        Some(replay) if !log.replaying => Some((replay.level, replay.seed, replay.ticks.len())),
+        _ => None,
+    };
+
+    if let Some((level, seed, ticks)) = pending {
+        println!(
+            "Replaying level {} with seed {} ({} ticks)",
+            level, seed, ticks
+        );
+
+        next_level.0 = level;
+        rng.reseed(seed);
+        log.replaying = true;
+    }
+
+    log.recording = Recording::new(next_level.0, rng.seed());
+}
+
+/// Replays have nothing more to say once the level is over
+pub fn stop_replay(mut log: ResMut<InputLog>) {
+    if log.replaying {
+        println!("Replay finished");
+        log.replay = None;
+        log.replaying = false;
+    }
+}
+
+/// Feeds the replay into the input on each tick, then records the input
+pub fn replay_input(
+    clock: Res<SimClock>,
+    mut input: ResMut<InputState>,
+    mut log: ResMut<InputLog>,
+) {
+    if !clock.ticked() {
+        return;
+    }
+
+    if log.replaying {
+        let tick = log
+            .replay
+            .as_ref()
+            .and_then(|replay| replay.ticks.get(log.recording.ticks.len()).copied());
+
+        match tick {
+            Some(tick) => tick.apply(&mut input),
+            None => {
+                println!("Replay ran out of input, handing back to the keyboard");
+                log.replay = None;
+                log.replaying = false;
+            }
+        }
+    }
+
+    let tick = TickInput::from_input(&input);
+    log.recording.ticks.push(tick);
+}
+
+/// Saves the recording of the current level when F8 is pressed
+pub fn save_recording(keys: Res<Input<KeyCode>>, log: Res<InputLog>) {
+    if keys.just_pressed(KeyCode::F8) {
+        write_recording(&log.recording);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_recording(recording: &Recording) {
+    use std::{fs, path::Path};
+
+    let path = Path::new(REPLAY_DIR).join(format!(
+        "level-{}-{}.replay",
+        recording.level, recording.seed
+    ));
+
+    match fs::create_dir_all(REPLAY_DIR).and_then(|_| fs::write(&path, recording.to_bytes())) {
+        Ok(_) => println!(
+            "Saved {} ticks of input to {}",
+            recording.ticks.len(),
+            path.display()
+        ),
+        Err(e) => println!("Unable to save replay to {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_recording(_recording: &Recording) {
+    println!("Saving replays isn't supported in the browser");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(flags: u8, x: u16, y: u16) -> TickInput {
+        TickInput {
+            flags,
+            cursor: (x, y),
+        }
+    }
+
+    #[test]
+    fn round_trips_long_runs() {
+        let mut recording = Recording::new(7, u64::MAX - 3);
+        recording.ticks = vec![tick(LEFT | JUMP, 3, 400); u16::MAX as usize + 10];
+        recording.ticks.push(tick(RIGHT, 0, 0));
+        recording.ticks.push(tick(MOUSE, u16::MAX, 1));
+        recording.ticks.push(tick(MOUSE, u16::MAX, 1));
+
+        let bytes = recording.to_bytes();
+        assert_eq!(bytes.len(), HEADER_LEN + 4 * RUN_LEN);
+        assert_eq!(Recording::from_bytes(&bytes).unwrap(), recording);
+    }
+
+    #[test]
+    fn round_trips_an_empty_recording() {
+        let recording = Recording::new(2, 42);
+        let bytes = recording.to_bytes();
+
+        assert_eq!(bytes.len(), HEADER_LEN);
+        assert_eq!(Recording::from_bytes(&bytes).unwrap(), recording);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = Recording::new(2, 42).to_bytes();
+        bytes[0] = b'X';
+        assert!(Recording::from_bytes(&bytes).is_err());
+
+        assert!(Recording::from_bytes(b"SFR1").is_err());
+        assert!(Recording::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let mut recording = Recording::new(2, 42);
+        recording.ticks.push(tick(LEFT, 1, 2));
+
+        let bytes = recording.to_bytes();
+        assert!(Recording::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_runs() {
+        let mut recording = Recording::new(2, 42);
+        recording.ticks.push(tick(LEFT, 1, 2));
+
+        let mut bytes = recording.to_bytes();
+        let count = bytes.len() - 2;
+        bytes[count..].copy_from_slice(&0u16.to_le_bytes());
+        assert!(Recording::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn input_survives_a_round_trip_through_a_tick() {
+        let input = InputState {
+            left_pressed: true,
+            mouse_down: true,
+            cursor_pos: Vec2::new(12.7, 3.2),
+            ..Default::default()
+        };
+
+        let recorded = TickInput::from_input(&input);
+        let mut replayed = InputState::default();
+        recorded.apply(&mut replayed);
+
+        assert!(replayed.left_pressed && replayed.mouse_down);
+        assert!(!replayed.right_pressed && !replayed.jump_pressed);
+        assert_eq!(TickInput::from_input(&replayed), recorded);
+    }
+}
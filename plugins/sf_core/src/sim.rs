@@ -0,0 +1,131 @@
+//! Gameplay moves on in fixed ticks with its own random numbers, so a level played
+//! with the same seed and the same input on each tick always plays out the same way,
+//! however fast the game happens to be running.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, thread_rng, Rng, RngCore, SeedableRng};
+
+use crate::GameState;
+
+/// The length of a gameplay tick in seconds
+pub const SIM_STEP: f64 = 1. / 60.;
+
+/// Counts gameplay ticks since the level started. Systems that change the game
+/// should only do so on frames where `ticked` is true, and use `seconds` rather
+/// than the wall clock for anything they schedule.
+#[derive(Default)]
+pub struct SimClock {
+    tick: u64,
+    accumulator: f64,
+    ticked: bool,
+}
+
+impl SimClock {
+    /// The number of ticks since the level started
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// The gameplay time since the level started
+    pub fn seconds(&self) -> f64 {
+        self.tick as f64 * SIM_STEP
+    }
+
+    /// True if gameplay should move on by one tick this frame
+    pub fn ticked(&self) -> bool {
+        self.ticked
+    }
+
+    /// True once the given gameplay time has been reached, allowing for rounding
+    pub fn reached(&self, at: f64) -> bool {
+        self.seconds() + SIM_STEP / 2. >= at
+    }
+
+    fn reset(&mut self) {
+        *self = SimClock::default();
+    }
+}
+
+/// The random numbers used by gameplay, reseeded at the start of each level
+pub struct SimRng {
+    seed: u64,
+    rng: StdRng,
+    /// when set every level starts from this seed, otherwise each one gets a new seed
+    pub fixed_seed: Option<u64>,
+}
+
+impl SimRng {
+    pub fn new(fixed_seed: Option<u64>) -> Self {
+        let seed = fixed_seed.unwrap_or_else(|| thread_rng().gen());
+
+        SimRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            fixed_seed,
+        }
+    }
+
+    /// The seed the current level was started from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        SimRng::new(None)
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Winds the clock back to zero and reseeds the random numbers as a level is loaded
+pub fn start_sim(mut clock: ResMut<SimClock>, mut rng: ResMut<SimRng>) {
+    clock.reset();
+
+    let seed = rng.fixed_seed.unwrap_or_else(|| thread_rng().gen());
+    rng.reseed(seed);
+    println!("Starting level with seed {}", seed);
+}
+
+/// Works out whether gameplay should tick this frame. At most one tick runs per
+/// frame, so if the game can't keep up it slows down rather than skipping ahead.
+pub fn advance_sim_clock(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut clock: ResMut<SimClock>,
+) {
+    clock.ticked = false;
+    if *state.current() != GameState::Playing {
+        return;
+    }
+
+    // hold on to at most one tick of spare time, so a fast frame after a slow one can catch up
+    clock.accumulator = (clock.accumulator + time.delta_seconds_f64()).min(2. * SIM_STEP);
+    if clock.accumulator >= SIM_STEP {
+        clock.accumulator -= SIM_STEP;
+        clock.tick += 1;
+        clock.ticked = true;
+    }
+}
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+use sf_core::{
+    colors::{to_u8s, Colors},
+    dims::Dims,
+    map::Map,
+};
+
+use crate::Editor;
+
+/// Redraws the level being edited whenever it changes
+pub fn draw_editor(
+    mut dims: ResMut<Dims>,
+    colours: Res<Colors>,
+    mut map: ResMut<Map>,
+    mut editor: ResMut<Editor>,
+) {
+    if !editor.dirty {
+        return;
+    }
+    editor.dirty = false;
+
+    let size = editor.level.grid_size();
+    if size != (dims.tex_w, dims.tex_h) {
+        dims.set_grid_size(size);
+        map.resize(&dims);
+    }
+
+    let bg = to_u8s(colours.background);
+    map.clear(&dims, &bg);
+
+    let mut draw = |pos: (u32, u32), col: Color| {
+        if pos.0 < dims.tex_w && pos.1 < dims.tex_h {
+            map.set_pixel(&dims, pos.0, pos.1, to_u8s(col));
+        }
+    };
+
+    for &pos in editor.walls.iter() {
+        draw(pos, colours.walls);
+    }
+
+    for particle in editor.level.particles.iter() {
+        draw(particle.pos, colours.get(particle.color));
+    }
+
+    for spawner in editor.level.spawners.iter() {
+        draw(spawner.pos, colours.get(spawner.color));
+    }
+
+    for sink in editor.level.sinks.iter() {
+        draw(sink.pos, Color::FUCHSIA);
+    }
+
+    draw(editor.level.player_spawn, Color::GREEN);
+}
@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+use sf_core::{
+    colors::ColorName,
+    dims::Dims,
+    entity::{ParticleType, Sink, Spawner},
+    input::InputState,
+    levels::{NextLevel, PlaytestLevel},
+    GameState,
+};
+
+use crate::{save_level::save_level, Editor, Tool};
+
+/// How fast the arrow keys scroll around levels bigger than the screen, in cells per second
+const PAN_SPEED: f32 = 60.;
+
+/// Picks the current tool and adjusts what it places
+pub fn editor_tools(keys: Res<Input<KeyCode>>, mut editor: ResMut<Editor>) {
+    let tools = [
+        (KeyCode::Key1, Tool::Wall),
+        (KeyCode::Key2, Tool::Erase),
+        (KeyCode::Key3, Tool::Spawner),
+        (KeyCode::Key4, Tool::Sink),
+        (KeyCode::Key5, Tool::PlayerSpawn),
+    ];
+
+    for &(key, tool) in tools.iter() {
+        if keys.just_pressed(key) {
+            editor.tool = tool;
+        }
+    }
+
+    if keys.just_pressed(KeyCode::T) {
+        editor.particle_type = match editor.particle_type {
+            ParticleType::Sand => ParticleType::Liquid,
+            ParticleType::Liquid => ParticleType::Water,
+            _ => ParticleType::Sand,
+        };
+    }
+
+    if keys.just_pressed(KeyCode::C) {
+        editor.color = match editor.color {
+            ColorName::Sand => ColorName::BlueSand,
+            ColorName::BlueSand => ColorName::RedSand,
+            ColorName::RedSand => ColorName::GlowSlime,
+            ColorName::GlowSlime => ColorName::Water,
+            _ => ColorName::Sand,
+        };
+    }
+
+    if keys.just_pressed(KeyCode::LBracket) {
+        editor.delay = (editor.delay / 2.).max(0.01);
+    }
+
+    if keys.just_pressed(KeyCode::RBracket) {
+        editor.delay = (editor.delay * 2.).min(10.);
+    }
+
+    if keys.just_pressed(KeyCode::Minus) {
+        editor.limit = editor.limit.saturating_sub(10).max(10);
+    }
+
+    if keys.just_pressed(KeyCode::Equals) {
+        editor.limit = editor.limit.saturating_add(10);
+    }
+}
+
+/// Scrolls the view with the arrow keys
+pub fn editor_pan(time: Res<Time>, keys: Res<Input<KeyCode>>, mut dims: ResMut<Dims>) {
+    let mut direction = Vec2::ZERO;
+    if keys.pressed(KeyCode::Left) {
+        direction.x -= 1.;
+    }
+    if keys.pressed(KeyCode::Right) {
+        direction.x += 1.;
+    }
+    if keys.pressed(KeyCode::Down) {
+        direction.y -= 1.;
+    }
+    if keys.pressed(KeyCode::Up) {
+        direction.y += 1.;
+    }
+
+    if direction == Vec2::ZERO {
+        return;
+    }
+
+    let step = direction * PAN_SPEED * dims.tex_stride as f32 * time.delta_seconds();
+    dims.camera = dims.clamp_camera(dims.camera + step);
+}
+
+/// Applies the current tool under the mouse. Walls paint while the mouse is held,
+/// spawners and sinks are placed once per click.
+pub fn editor_painting(
+    input: Res<InputState>,
+    dims: Res<Dims>,
+    mut editor: ResMut<Editor>,
+    mut was_down: Local<bool>,
+) {
+    let x = input.cursor_pos.x.floor();
+    let y = input.cursor_pos.y.floor();
+    let cursor = if x >= 0. && y >= 0. && (x as u32) < dims.tex_w && (y as u32) < dims.tex_h {
+        Some((x as u32, y as u32))
+    } else {
+        None
+    };
+
+    if editor.cursor != cursor {
+        editor.cursor = cursor;
+    }
+
+    let clicked = input.mouse_down && !*was_down;
+    *was_down = input.mouse_down;
+
+    let pos = match cursor {
+        Some(pos) if input.mouse_down => pos,
+        _ => return,
+    };
+
+    let tool = editor.tool;
+    match tool {
+        Tool::Wall => {
+            if editor.walls.insert(pos) {
+                editor.dirty = true;
+            }
+        }
+        Tool::Erase => {
+            let level = &mut editor.level;
+            let before = level.spawners.len() + level.sinks.len() + level.particles.len();
+            level.spawners.retain(|s| s.pos != pos);
+            level.sinks.retain(|s| s.pos != pos);
+            level.particles.retain(|p| p.pos != pos);
+            let after = level.spawners.len() + level.sinks.len() + level.particles.len();
+
+            if editor.walls.remove(&pos) || before != after {
+                editor.dirty = true;
+            }
+        }
+        Tool::Spawner if clicked => {
+            let spawner = Spawner {
+                pos,
+                spawn_limit: editor.limit,
+                spawn_delay: editor.delay,
+                initial_vel: (0, -1),
+                color: editor.color,
+                next_spawn: 0.,
+                particle_type: editor.particle_type,
+                light: None,
+            };
+
+            editor.level.spawners.push(spawner);
+            editor.dirty = true;
+        }
+        Tool::Sink if clicked => {
+            let sink = Sink {
+                pos,
+                sink_rate: editor.delay,
+                next_sink: 0.,
+                sink_limit: editor.limit,
+            };
+
+            editor.level.sinks.push(sink);
+            editor.dirty = true;
+        }
+        Tool::PlayerSpawn => {
+            if editor.level.player_spawn != pos {
+                editor.level.player_spawn = pos;
+                editor.dirty = true;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Play testing, saving and leaving the editor
+pub fn editor_commands(
+    keys: Res<Input<KeyCode>>,
+    editor: Res<Editor>,
+    mut playtest: ResMut<PlaytestLevel>,
+    mut next_level: ResMut<NextLevel>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if ctrl && keys.just_pressed(KeyCode::S) {
+        save_level(&editor.path, &editor.to_level());
+    }
+
+    // F2 isn't used here, it is still pressed in the frame the editor is entered
+    if keys.just_pressed(KeyCode::P) {
+        if state.set(GameState::Loading).is_ok() {
+            println!("Play testing {}", editor.path);
+            playtest.level = Some(editor.to_level());
+            playtest.path = Some(editor.path.clone());
+        }
+    } else if keys.just_pressed(KeyCode::Escape) && state.set(GameState::Loading).is_ok() {
+        // go back to the level that was being played before the editor was opened
+        println!("Leaving editor");
+        playtest.level = None;
+        playtest.path = None;
+        next_level.0 = next_level.0.saturating_sub(1).max(1);
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::{Editor, EditorUiElement, Tool};
+
+pub struct EditorStatus;
+
+const HELP: &str = "1 wall  2 erase  3 spawner  4 sink  5 player  T type  C colour  [ ] delay  - = limit  P play  Ctrl+S save  Esc exit";
+
+pub fn spawn_editor_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/PressStart2P-Regular.ttf"),
+        font_size: 10.,
+        color: Color::rgb(0.5, 0.5, 0.5),
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::FlexStart,
+                align_items: AlignItems::FlexStart,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(EditorUiElement)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(5.0)),
+                    ..Default::default()
+                },
+                text: Text::with_section(HELP, text_style.clone(), Default::default()),
+                ..Default::default()
+            });
+
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section("", text_style, Default::default()),
+                    ..Default::default()
+                })
+                .insert(EditorStatus);
+        });
+
+    println!("Spawned editor UI");
+}
+
+pub fn update_editor_ui(editor: Res<Editor>, mut statuses: Query<&mut Text, With<EditorStatus>>) {
+    let tool = match editor.tool {
+        Tool::Wall => "wall".to_string(),
+        Tool::Erase => "erase".to_string(),
+        Tool::Spawner => format!(
+            "spawner {:?} {:?} every {:.2}s, limit {}",
+            editor.particle_type, editor.color, editor.delay, editor.limit
+        ),
+        Tool::Sink => format!("sink every {:.2}s, limit {}", editor.delay, editor.limit),
+        Tool::PlayerSpawn => "player spawn".to_string(),
+    };
+
+    let cursor = match editor.cursor {
+        Some((x, y)) => format!("[{},{}]", x, y),
+        None => "".into(),
+    };
+
+    for mut text in statuses.iter_mut() {
+        text.sections[0].value = format!("{}  {}  {}", editor.path, tool, cursor);
+    }
+}
+
+pub fn despawn_editor_ui(mut commands: Commands, items: Query<Entity, With<EditorUiElement>>) {
+    for ent in items.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+use sf_core::GameState;
+
+/// Switches to the level editor when F2 is pressed
+pub fn enter_editor(keys: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keys.just_pressed(KeyCode::F2) && state.set(GameState::Editor).is_ok() {
+        println!("Entering editor");
+    }
+}
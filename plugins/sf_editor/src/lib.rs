@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use sf_core::{
+    colors::ColorName,
+    entity::ParticleType,
+    levels::{Level, Wall},
+    GameState,
+};
+
+mod draw_editor;
+mod editor_input;
+mod editor_ui;
+mod enter_editor;
+mod save_level;
+mod setup_editor;
+
+use draw_editor::draw_editor;
+use editor_input::{editor_commands, editor_painting, editor_pan, editor_tools};
+use editor_ui::{despawn_editor_ui, spawn_editor_ui, update_editor_ui};
+use enter_editor::enter_editor;
+use setup_editor::setup_editor;
+
+/// Where levels are saved if they weren't loaded from a file
+pub const DEFAULT_LEVEL_PATH: &str = "levels/editor.level";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tool {
+    Wall,
+    Erase,
+    Spawner,
+    Sink,
+    PlayerSpawn,
+}
+
+pub struct EditorUiElement;
+
+/// The level being edited along with the settings for the current tool
+pub struct Editor {
+    pub level: Level,
+    pub walls: HashSet<(u32, u32)>,
+    pub path: String,
+
+    pub tool: Tool,
+    pub particle_type: ParticleType,
+    pub color: ColorName,
+    pub delay: f64,
+    pub limit: u32,
+
+    pub cursor: Option<(u32, u32)>,
+    pub dirty: bool,
+}
+
+impl Editor {
+    pub fn new(mut level: Level, path: String) -> Self {
+        let walls = level.walls.drain(..).flat_map(|w| w.points()).collect();
+
+        // the painted walls replace any image the layout came from
+        level.image = None;
+
+        Editor {
+            level,
+            walls,
+            path,
+            tool: Tool::Wall,
+            particle_type: ParticleType::Sand,
+            color: ColorName::Sand,
+            delay: 0.1,
+            limit: 100,
+            cursor: None,
+            dirty: true,
+        }
+    }
+
+    /// Builds the level, joining the painted walls back up into horizontal runs
+    pub fn to_level(&self) -> Level {
+        let mut points = self.walls.iter().copied().collect::<Vec<_>>();
+        points.sort_by_key(|&(x, y)| (y, x));
+
+        let mut walls: Vec<Wall> = vec![];
+        for (x, y) in points {
+            match walls.last_mut() {
+                Some(Wall::Horizontal { x_range, y: wy }) if *wy == y && x_range.1 == x => {
+                    x_range.1 += 1;
+                }
+                _ => walls.push(Wall::from_x_range(x..x + 1, y)),
+            }
+        }
+
+        Level {
+            walls,
+            ..self.level.clone()
+        }
+    }
+}
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing).with_system(enter_editor.system()),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Editor)
+                .with_system(setup_editor.system())
+                .with_system(spawn_editor_ui.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Editor)
+                .with_system(editor_tools.system().label("editor_tools"))
+                .with_system(editor_pan.system().before("update_camera"))
+                .with_system(
+                    editor_painting
+                        .system()
+                        .label("editor_painting")
+                        .after("editor_tools"),
+                )
+                .with_system(editor_commands.system().after("editor_painting"))
+                .with_system(draw_editor.system().after("editor_painting"))
+                .with_system(update_editor_ui.system().after("editor_painting")),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Editor).with_system(despawn_editor_ui.system()),
+        );
+    }
+}
@@ -0,0 +1,23 @@
+use sf_core::levels::Level;
+
+/// Writes the level into the assets folder, `path` is relative to the assets folder
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_level(path: &str, level: &Level) {
+    use ron::ser::{to_string_pretty, PrettyConfig};
+    use std::{fs, path::Path};
+
+    let full_path = Path::new("assets").join(path);
+
+    match to_string_pretty(level, PrettyConfig::new()) {
+        Ok(contents) => match fs::write(&full_path, contents) {
+            Ok(_) => println!("Saved level to {}", full_path.display()),
+            Err(e) => println!("Unable to save level to {}: {}", full_path.display(), e),
+        },
+        Err(e) => println!("Unable to serialise level: {}", e),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_level(_path: &str, _level: &Level) {
+    println!("Saving levels isn't supported in the browser");
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use sf_core::{
+    level_assets::{LevelAssets, LevelManifest},
+    levels::{Level, NextLevel, PlaytestLevel},
+    render::render_pipeline::LightSource,
+};
+
+use crate::{Editor, DEFAULT_LEVEL_PATH};
+
+/// Loads the level that was being played into the editor. Coming back from a
+/// play test keeps the existing editor so nothing is lost.
+pub fn setup_editor(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    next_level: Res<NextLevel>,
+    playtest: Res<PlaytestLevel>,
+    level_assets: Res<LevelAssets>,
+    manifests: Res<Assets<LevelManifest>>,
+    levels: Res<Assets<Level>>,
+    editor: Option<ResMut<Editor>>,
+    mut lighting: Query<&mut LightSource>,
+) {
+    // light up the whole level
+    let mut light = lighting.single_mut().unwrap();
+    light.ambient = 1.;
+
+    match editor {
+        Some(mut editor) if playtest.level.is_some() => {
+            editor.dirty = true;
+        }
+        _ => {
+            // spawn_level has already moved on to the level after this one
+            let handle = manifests
+                .get(&level_assets.manifest)
+                .zip((next_level.0 as usize).checked_sub(2))
+                .and_then(|(manifest, idx)| manifest.levels.get(idx));
+
+            let editor = match handle.and_then(|h| levels.get(h).map(|level| (h, level))) {
+                Some((handle, level)) => {
+                    let path = asset_server
+                        .get_handle_path(handle)
+                        .map(|path| path.path().to_string_lossy().into_owned())
+                        .unwrap_or_else(|| DEFAULT_LEVEL_PATH.into());
+
+                    Editor::new(level.clone(), path)
+                }
+                None => Editor::new(empty_level(), DEFAULT_LEVEL_PATH.into()),
+            };
+
+            println!("Editing {}", editor.path);
+            commands.insert_resource(editor);
+        }
+    }
+}
+
+fn empty_level() -> Level {
+    Level {
+        image: None,
+        image_path: None,
+        size: None,
+        player_spawn: (5, 50),
+        player_slime_target: 20,
+        walls: vec![],
+        particles: vec![],
+        spawners: vec![],
+        sinks: vec![],
+        lights: vec![],
+        message: "".into(),
+        starting_light: 20,
+        max_light: 20,
+        light_decay: 1.5,
+        player: Default::default(),
+    }
+}
@@ -2,12 +2,13 @@ use bevy::prelude::*;
 use sf_core::{
     colors::{to_u8s, Colors},
     dims::Dims,
-    entity::{Particle, Sink, Spawner},
+    entity::{LightEmitter, Sink, Spawner},
+    ghost::Ghost,
     levels::LevelMessage,
     map::Map,
     render::render_pipeline::LightSource,
     ui::PlayingUiElement,
-    Player,
+    Player, PlayerState,
 };
 
 pub fn despawner(
@@ -15,21 +16,20 @@ pub fn despawner(
     dims: Res<Dims>,
     colours: Res<Colors>,
     mut map: ResMut<Map>,
-    mut particles: Query<(&Particle, Entity)>,
     mut players: Query<(&Player, Entity)>,
     mut spawners: Query<(&Spawner, Entity)>,
     mut sinks: Query<(&Sink, Entity)>,
+    fixtures: Query<Entity, (With<LightEmitter>, Without<Spawner>)>,
+    ghosts: Query<Entity, With<Ghost>>,
     mut ui: Query<(&PlayingUiElement, Entity)>,
     mut level_messages: Query<(&LevelMessage, Entity)>,
     mut lighting: Query<&mut LightSource>,
 ) {
-    // despawn entities and UI
-    for (_, ent) in particles.iter_mut() {
-        commands.entity(ent).despawn_recursive();
-    }
-
-    for (_, ent) in players.iter_mut() {
-        commands.entity(ent).despawn_recursive();
+    // despawn entities and UI, a dead player stays for the game over screen
+    for (player, ent) in players.iter_mut() {
+        if player.state != PlayerState::Dead {
+            commands.entity(ent).despawn_recursive();
+        }
     }
 
     for (_, ent) in ui.iter_mut() {
@@ -48,12 +48,27 @@ pub fn despawner(
         commands.entity(ent).despawn_recursive();
     }
 
+    for ent in fixtures.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+
+    for ent in ghosts.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+
     // turn off the lights
     let mut light = lighting.single_mut().unwrap();
-    light.strength = 0.001;
-    light.pos = Vec2::ZERO;
+    light.ambient = 0.;
+    light.light_count = 0.;
 
     // clear the map
     let bg = to_u8s(colours.background);
     map.clear(&dims, &bg);
 }
+
+/// Removes the dead player left on the game over screen by `despawner`
+pub fn dead_player_despawner(mut commands: Commands, players: Query<Entity, With<Player>>) {
+    for ent in players.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
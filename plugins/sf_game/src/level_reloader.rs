@@ -0,0 +1,141 @@
+use bevy::{prelude::*, render::texture::TextureFormat};
+use sf_core::{
+    level_assets::{LevelAssets, LevelManifest},
+    level_image::{apply_pixels, check_size},
+    levels::{Level, NextLevel, PlaytestLevel},
+    GameState,
+};
+
+/// Restarts the current level in place when its file changes on disk or F5 is pressed.
+/// Going back through `GameState::Loading` despawns the old level and respawns the player.
+/// Runs in every state so file changes aren't missed, but only restarts outside the menu
+/// and the editor. A play test is replaced by the saved level when its file changes.
+pub fn level_reloader(
+    keys: Res<Input<KeyCode>>,
+    mut level_events: EventReader<AssetEvent<Level>>,
+    mut manifest_events: EventReader<AssetEvent<LevelManifest>>,
+    asset_server: Res<AssetServer>,
+    level_assets: Res<LevelAssets>,
+    manifests: Res<Assets<LevelManifest>>,
+    levels: Res<Assets<Level>>,
+    mut next_level: ResMut<NextLevel>,
+    mut playtest: ResMut<PlaytestLevel>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // spawn_level has already moved on to the level after this one
+    let current = next_level.0.saturating_sub(1);
+    let current_handle = manifests
+        .get(&level_assets.manifest)
+        .and_then(|manifest| manifest.levels.get((current as usize).saturating_sub(1)));
+
+    let mut level_changed = false;
+    for ev in level_events.iter() {
+        let handle = match ev {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+
+        let is_playtest = playtest.path.is_some()
+            && asset_server
+                .get_handle_path(handle)
+                .map(|path| path.path().to_string_lossy().into_owned())
+                == playtest.path;
+
+        if is_playtest {
+            if let Some(level) = levels.get(handle) {
+                playtest.level = Some(level.clone());
+            }
+        }
+
+        let watched = if playtest.level.is_some() {
+            is_playtest
+        } else {
+            Some(handle) == current_handle
+        };
+        level_changed |= watched;
+    }
+
+    let manifest_changed = manifest_events
+        .iter()
+        .filter(|ev| matches!(ev, AssetEvent::Modified { .. }))
+        .count()
+        > 0;
+
+    if !(keys.just_pressed(KeyCode::F5) || level_changed || manifest_changed) {
+        return;
+    }
+
+    if !matches!(
+        state.current(),
+        GameState::Playing | GameState::GameOver | GameState::Victory
+    ) {
+        return;
+    }
+
+    if state.set(GameState::Loading).is_ok() {
+        println!("Reloading level {}", current);
+
+        // play tests don't move through the manifest, so are already on the right level
+        if playtest.level.is_none() {
+            next_level.0 = current;
+        }
+    }
+}
+
+/// Repaints levels built from a PNG when the PNG changes on disk. The level asset
+/// changing then restarts the level through `level_reloader`. As when loading, an
+/// image that no longer matches the level's size is skipped.
+pub fn level_image_reloader(
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    asset_server: Res<AssetServer>,
+    textures: Res<Assets<Texture>>,
+    mut levels: ResMut<Assets<Level>>,
+) {
+    for ev in texture_events.iter() {
+        let handle = match ev {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+
+        // the main texture changes every frame, but wasn't loaded from a file
+        let path = match asset_server.get_handle_path(handle) {
+            Some(path) => path.path().to_path_buf(),
+            None => continue,
+        };
+
+        let texture = match textures.get(handle) {
+            Some(texture) => texture,
+            None => continue,
+        };
+
+        if !matches!(
+            texture.format,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+        ) {
+            println!(
+                "Unable to reload {}, it isn't an RGBA image",
+                path.display()
+            );
+            continue;
+        }
+
+        let painted: Vec<_> = levels
+            .iter()
+            .filter(|(_, level)| level.image_path.as_ref() == Some(&path))
+            .map(|(id, _)| id)
+            .collect();
+
+        for id in painted {
+            if let Some(level) = levels.get_mut(id) {
+                let size = (texture.size.width, texture.size.height);
+                let repainted = check_size(level, size.0, size.1)
+                    .and_then(|_| apply_pixels(level, size.0, size.1, &texture.data));
+
+                match repainted {
+                    Ok(_) => println!("Repainted level from {}", path.display()),
+                    Err(e) => println!("Unable to reload {}: {}", path.display(), e),
+                }
+            }
+        }
+    }
+}
@@ -4,6 +4,7 @@ use lighting::{point_lighting, LightingStatus};
 use sf_core::GameState;
 
 pub mod despawner;
+pub mod level_reloader;
 pub mod lighting;
 pub mod sand_updater;
 pub mod sink_consumption;
@@ -24,11 +25,17 @@ impl Plugin for GamePlugin {
             enabled: true,
             disable_handled: false,
         })
+        // everything that changes the map runs in a fixed order so replays match,
+        // following on from the player systems:
+        // replay_input -> calculate_player_movement -> player_sink -> sink_consumption
+        // -> sand_updater -> spawner_emission
         .add_system_set(
             SystemSet::on_update(GameState::Playing)
                 .with_system(
                     sink_consumption::sink_consumption
                         .system()
+                        .label("sink_consumption")
+                        .after("player_sink")
                         .before(MenuStage::Movement),
                 )
                 .with_system(
@@ -49,9 +56,15 @@ impl Plugin for GamePlugin {
                         .after(MenuStage::Spawning),
                 ),
         )
+        .add_system(level_reloader::level_reloader.system())
+        .add_system(level_reloader::level_image_reloader.system())
         .add_system_set(
             SystemSet::on_exit(GameState::Playing).with_system(despawner::despawner.system()),
         )
+        .add_system_set(
+            SystemSet::on_exit(GameState::GameOver)
+                .with_system(despawner::dead_player_despawner.system()),
+        )
         .add_system_set(
             SystemSet::on_enter(GameState::Victory).with_system(despawner::despawner.system()),
         );
@@ -1,8 +1,17 @@
 use bevy::{
     math::Vec2,
-    prelude::{Query, Res, ResMut},
+    prelude::{Assets, Query, Res, ResMut, Texture},
 };
-use sf_core::{dims::Dims, render::render_pipeline::LightSource, LightingTarget, Position};
+use sf_core::{
+    dims::Dims,
+    entity::LightEmitter,
+    render::{
+        lights::{write_lights, PointLight, DEFAULT_FALLOFF},
+        render_pipeline::LightSource,
+    },
+    Hitbox, LightingTarget, Position,
+};
+use std::cmp::Ordering;
 
 #[derive(Default)]
 pub struct LightingStatus {
@@ -14,34 +23,74 @@ pub struct LightingStatus {
 pub fn point_lighting(
     mut status: ResMut<LightingStatus>,
     mut world: Query<&mut LightSource>,
+    mut textures: ResMut<Assets<Texture>>,
     dims: Res<Dims>,
-    mut lights: Query<(&LightingTarget, &Position)>,
+    players: Query<(&LightingTarget, &Position, Option<&Hitbox>)>,
+    emitters: Query<(&LightEmitter, &Position)>,
 ) {
+    let mut shader_data = match world.single_mut() {
+        Ok(shader_data) => shader_data,
+        _ => return,
+    };
+
     if !status.enabled {
         if status.disable_handled {
             return;
         }
 
-        // "disable" lighting by setting a massive distance.
+        // "disable" lighting by lighting everything
         println!("Disabling lighting");
-        let mut shader_data = world.single_mut().unwrap();
-        shader_data.strength = 100000.;
+        shader_data.ambient = 1.;
+        shader_data.light_count = 0.;
         status.disable_handled = true;
+        return;
     }
 
-    // for now should only be one light :(
-    match lights.single_mut() {
-        Ok((light, pos)) => match world.single_mut() {
-            Ok(mut shader_data) => {
-                shader_data.strength = light.lighting_strength as f32;
-                shader_data.pos = Vec2::new(pos.0 as f32, (dims.tex_h - pos.1) as f32);
-            }
-            _ => {
-                status.enabled = false;
-            }
-        },
-        _ => {
-            status.enabled = false;
-        }
+    // lights sit in the middle of their cells (or hitbox), and the texture is upside
+    // down compared to the grid
+    let to_texture = |pos: &Position, size: (u32, u32)| {
+        Vec2::new(
+            pos.0 as f32 + size.0 as f32 / 2.,
+            dims.tex_h as f32 - pos.1 as f32 - size.1 as f32 / 2.,
+        )
+    };
+
+    // the player's light goes first so it is never dropped, then the brightest others
+    let mut lights = players
+        .iter()
+        .map(|(light, pos, hitbox)| PointLight {
+            pos: to_texture(pos, hitbox.map_or((1, 1), |h| (h.width, h.height))),
+            strength: light.lighting_strength as f32,
+            color: [255, 255, 255],
+            falloff: DEFAULT_FALLOFF,
+        })
+        .collect::<Vec<_>>();
+
+    // lights that can't reach the screen aren't worth a slot
+    let (view_min, view_max) = dims.visible_cells();
+    let mut others = emitters
+        .iter()
+        .filter(|(emitter, pos)| {
+            let cell = Vec2::new(pos.0 as f32, pos.1 as f32);
+            cell.distance(cell.max(view_min).min(view_max)) <= emitter.strength
+        })
+        .map(|(emitter, pos)| PointLight {
+            pos: to_texture(pos, (1, 1)),
+            strength: emitter.strength,
+            color: emitter.color,
+            falloff: emitter.falloff,
+        })
+        .collect::<Vec<_>>();
+    others.sort_by(|a, b| {
+        b.strength
+            .partial_cmp(&a.strength)
+            .unwrap_or(Ordering::Equal)
+    });
+    lights.extend(others);
+
+    if let Some(texture) = textures.get_mut(&shader_data.lights) {
+        shader_data.light_count = write_lights(texture, &lights) as f32;
     }
+
+    shader_data.ambient = 0.;
 }
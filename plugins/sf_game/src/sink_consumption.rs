@@ -4,17 +4,22 @@ use sf_core::{
     dims::Dims,
     entity::Sink,
     map::Map,
+    sim::SimClock,
 };
 
 pub fn sink_consumption(
     mut commands: Commands,
-    time: Res<Time>,
+    clock: Res<SimClock>,
     dims: Res<Dims>,
     colours: Res<Colors>,
     mut map: ResMut<Map>,
     mut sinks: Query<(&mut Sink, Entity)>,
 ) {
-    let now = time.seconds_since_startup();
+    if !clock.ticked() {
+        return;
+    }
+
+    let now = clock.seconds();
     let clear_colour = to_u8s(colours.background);
 
     for (mut sink, ent) in sinks.iter_mut() {
@@ -24,16 +29,15 @@ pub fn sink_consumption(
             continue;
         }
 
-        if now < sink.next_sink {
+        if !clock.reached(sink.next_sink) {
             continue;
         }
 
-        if let Some(ent) = map.get(sink.pos.0, sink.pos.1) {
+        if map.get(sink.pos.0, sink.pos.1).is_some() {
             sink.sink_limit -= 1;
             sink.next_sink = now + sink.sink_rate;
 
             map.destroy_at(sink.pos.0, sink.pos.1, &dims, &clear_colour);
-            commands.entity(ent).despawn();
         }
     }
 }
@@ -1,20 +1,27 @@
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use sf_core::{
+    colors::{to_u8s, Colors},
     dims::Dims,
     entity::{Particle, Spawner},
     map::Map,
+    sim::{SimClock, SimRng},
 };
 
 pub fn spawner_emission(
     mut commands: Commands,
-    time: Res<Time>,
+    clock: Res<SimClock>,
+    mut rng: ResMut<SimRng>,
     dims: Res<Dims>,
+    colours: Res<Colors>,
     mut map: ResMut<Map>,
     mut spawners: Query<(&mut Spawner, Entity)>,
 ) {
-    let now = time.seconds_since_startup();
-    let mut rng = thread_rng();
+    if !clock.ticked() {
+        return;
+    }
+
+    let now = clock.seconds();
 
     for (mut spawner, ent) in spawners.iter_mut() {
         if spawner.spawn_limit == 0 {
@@ -23,11 +30,11 @@ pub fn spawner_emission(
             continue;
         }
 
-        if now < spawner.next_spawn {
+        if !clock.reached(spawner.next_spawn) {
             continue;
         }
 
-        if let Some(_) = map.get(spawner.pos.0, spawner.pos.1) {
+        if map.get(spawner.pos.0, spawner.pos.1).is_some() {
             continue;
         }
 
@@ -35,15 +42,13 @@ pub fn spawner_emission(
         spawner.next_spawn = now + spawner.spawn_delay;
 
         let particle = Particle {
-            pos: Vec2::new(spawner.pos.0 as f32, spawner.pos.1 as f32),
-            vel: spawner.initial_vel.clone(),
-            color: spawner.color.clone(),
             particle_type: spawner.particle_type,
-            next_update: 0.,
+            vel: spawner.initial_vel,
+            color: to_u8s(colours.get(spawner.color)),
             is_left_first: rng.gen_bool(0.5),
+            emissive: spawner.color.is_emissive(),
         };
-        let entity = commands.spawn().insert(particle).id();
 
-        map.spawn_entity(&dims, particle, entity);
+        map.spawn(&dims, spawner.pos.0, spawner.pos.1, particle);
     }
 }
@@ -1,16 +1,79 @@
 use bevy::prelude::*;
+use sf_core::{sim::SimClock, Player, PlayerState};
+
+/// A run of frames in the sprite sheet and how long each one is shown for.
+/// The sheet faces right, the sprite is flipped when the player faces left.
+struct Animation {
+    first: u32,
+    frames: u32,
+    frame_time: f64,
+    looping: bool,
+    upside_down: bool,
+}
+
+impl Animation {
+    fn new(first: u32, frames: u32, frame_time: f64) -> Self {
+        Animation {
+            first,
+            frames,
+            frame_time,
+            looping: true,
+            upside_down: false,
+        }
+    }
+
+    fn once(self) -> Self {
+        Animation {
+            looping: false,
+            ..self
+        }
+    }
+
+    /// The frame to show the given number of seconds into the animation
+    fn frame(&self, elapsed: f64) -> u32 {
+        let step = (elapsed.max(0.) / self.frame_time) as u32;
+
+        self.first
+            + if self.looping {
+                step % self.frames
+            } else {
+                step.min(self.frames - 1)
+            }
+    }
+}
+
+fn animation(state: PlayerState) -> Animation {
+    match state {
+        PlayerState::Idle => Animation::new(0, 2, 0.5),
+        PlayerState::Running => Animation::new(0, 4, 0.12),
+        PlayerState::Jumping => Animation::new(4, 1, 1.).once(),
+        PlayerState::Falling => Animation::new(4, 1, 1.).once(),
+        PlayerState::Landing => Animation::new(1, 1, 1.).once(),
+        PlayerState::WallSliding => Animation::new(5, 2, 0.15),
+        PlayerState::Dead => Animation {
+            upside_down: true,
+            ..Animation::new(4, 1, 1.).once()
+        },
+    }
+}
 
 pub fn animate_player(
-    time: Res<Time>,
-    texture_atlases: Res<Assets<TextureAtlas>>,
-    mut query: Query<(&mut Timer, &mut TextureAtlasSprite, &Handle<TextureAtlas>)>,
+    clock: Res<SimClock>,
+    players: Query<(&Player, &Children)>,
+    mut sprites: Query<&mut TextureAtlasSprite>,
 ) {
-    for (mut timer, mut sprite, texture_atlas_handle) in query.iter_mut() {
-        timer.tick(time.delta());
-        if timer.finished() {
-            let texture_atlas = texture_atlases.get(texture_atlas_handle).unwrap();
-            sprite.index =
-                ((sprite.index as usize + 1) % (texture_atlas.textures.len() - 1)) as u32;
+    let t = clock.seconds();
+
+    for (player, children) in players.iter() {
+        let animation = animation(player.state);
+        let index = animation.frame(t - player.state_started);
+
+        for child in children.iter() {
+            if let Ok(mut sprite) = sprites.get_mut(*child) {
+                sprite.index = index;
+                sprite.flip_x = player.facing_left;
+                sprite.flip_y = animation.upside_down;
+            }
         }
     }
 }
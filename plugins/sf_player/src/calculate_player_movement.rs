@@ -1,52 +1,91 @@
 use bevy::prelude::*;
 use sf_core::{
+    colors::{to_u8s, Colors},
     dims::Dims,
-    entity::{Particle, ParticleType},
+    entity::ParticleType,
     input::InputState,
     map::Map,
-    AudioState, Player, Position,
+    player_config::PlayerConfig,
+    sim::SimClock,
+    AudioState, Hitbox, Player, Position,
 };
 
-const ACCELERATION: f32 = 7.5;
-const JUMP_SIZE: f32 = 400.;
-const TERMINAL_FALL_VELOCITY: f32 = -450.;
-const UPDATE_RATE: f32 = 1. / 60.;
-const AIR_SPEED_RATIO: f32 = 2.;
-
 pub fn calculate_player_movement(
-    time: Res<Time>,
+    clock: Res<SimClock>,
     input: Res<InputState>,
     dims: Res<Dims>,
     asset_server: Res<AssetServer>,
     audio_state: Res<AudioState>,
     audio: Res<bevy_kira_audio::Audio>,
+    colours: Res<Colors>,
     mut map: ResMut<Map>,
-    mut player_query: Query<(&mut Player, &mut Position, &mut Transform)>,
-    particles: Query<(&Particle, Entity)>,
+    config: Res<PlayerConfig>,
+    mut player_query: Query<(&mut Player, &mut Position, &mut Transform, &Hitbox)>,
 ) {
-    let t = time.seconds_since_startup();
+    if !clock.ticked() {
+        return;
+    }
+
+    let t = clock.seconds();
 
-    for (mut player, mut pos, mut tx) in player_query.iter_mut() {
-        // throttle the player controller at approx 60fps
-        if t < player.next_update {
+    for (mut player, mut pos, mut tx, hitbox) in player_query.iter_mut() {
+        // throttle the player controller to its update rate
+        if !clock.reached(player.next_update) {
             continue;
         }
-        player.next_update = t + UPDATE_RATE as f64;
+        player.next_update = t + config.update_rate as f64;
 
         let was_grounded = player.is_grounded;
-        player.is_grounded = is_grounded((pos.0, pos.1), &mut map, &particles);
+        player.is_grounded = is_grounded((pos.0, pos.1), hitbox, &map, &config);
 
         if !was_grounded && player.is_grounded {
-            player.did_jump = false;
             audio.play_in_channel(asset_server.load("sounds/land.ogg"), &audio_state.channel);
         }
 
+        // the jump is over once the player is back on the ground and no longer rising
+        if player.is_grounded && player.velocity.y <= 0. {
+            player.did_jump = false;
+        }
+
+        // remember when the player was last on the ground and last pressed jump, so
+        // jumps still work just after walking off a ledge or just before landing
+        if player.is_grounded {
+            player.last_grounded = t;
+        }
+
+        let jump_started = input.jump_pressed && !player.jump_held;
+        let jump_released = !input.jump_pressed && player.jump_held;
+        player.jump_held = input.jump_pressed;
+
+        if jump_started {
+            player.last_jump_pressed = t;
+        }
+
+        // letting go of jump early cuts the jump short
+        if jump_released && player.did_jump && player.velocity.y > 0. {
+            player.velocity.y *= config.jump_release_ratio;
+        }
+
         // calculate accelerations from inputs
         let mut dx: f32 =
             if input.left_pressed { -1. } else { 0. } + if input.right_pressed { 1. } else { 0. };
+
+        // walls only matter in the air, pressing into one while falling slides down it
+        player.wall_side = if player.is_grounded {
+            0
+        } else {
+            touching_wall((pos.0, pos.1), hitbox, &map, &config)
+        };
+        player.wall_sliding =
+            player.wall_side != 0 && dx == player.wall_side as f32 && player.velocity.y <= 0.;
+
+        // liquid and sand slow the player down, liquid also holds them up
+        let (liquid, sand) = submerged((pos.0, pos.1), hitbox, &map);
+        let move_speed = config.move_speed * (1. - sand * (1. - config.sand_speed_ratio));
+
         if !player.is_grounded {
             // slighlty less mobile in the air?
-            dx *= AIR_SPEED_RATIO;
+            dx *= config.air_speed_ratio;
         }
 
         if dx == 0. && player.velocity.x.abs() > 0. {
@@ -54,85 +93,261 @@ pub fn calculate_player_movement(
             dx = -player.velocity.x.signum();
         }
 
-        let dy = if player.is_grounded {
-            if input.jump_pressed && !player.did_jump {
-                // jump
-                player.did_jump = true;
-                audio.play_in_channel(asset_server.load("sounds/jump.ogg"), &audio_state.channel);
-                JUMP_SIZE
-            } else {
-                // stay on the ground
-                0.
-            }
+        let can_jump = !player.did_jump
+            && t - player.last_grounded <= config.coyote_time
+            && t - player.last_jump_pressed <= config.jump_buffer;
+        let can_wall_jump = !can_jump
+            && player.wall_side != 0
+            && t - player.last_jump_pressed <= config.jump_buffer;
+
+        let mut dy = if can_jump {
+            // jump, replacing any fall speed picked up since leaving the ground
+            player.did_jump = true;
+            player.last_jump_pressed = f64::NEG_INFINITY;
+            audio.play_in_channel(asset_server.load("sounds/jump.ogg"), &audio_state.channel);
+            config.jump_size - player.velocity.y
+        } else if can_wall_jump {
+            // kick off away from the wall
+            player.did_jump = true;
+            player.last_jump_pressed = f64::NEG_INFINITY;
+            player.velocity.x = -player.wall_side as f32 * config.wall_jump_push;
+            audio.play_in_channel(asset_server.load("sounds/jump.ogg"), &audio_state.channel);
+            config.jump_size - player.velocity.y
+        } else if player.is_grounded {
+            // stay on the ground
+            0.
         } else {
-            // fall
-            -2.5 * ACCELERATION
+            // fall, more slowly when in liquid
+            -config.gravity * (1. - liquid * (1. - config.liquid_gravity_ratio))
+                + config.buoyancy * liquid
         };
 
+        // holding jump swims upwards
+        if liquid > 0. && input.jump_pressed && !player.is_grounded {
+            dy += config.swim_strength * liquid;
+        }
+
         // calculate velocity
-        let next_vel = Vec2::new(
-            (player.velocity.x + ACCELERATION * dx).clamp(-player.move_speed, player.move_speed),
-            (player.velocity.y + dy).max(TERMINAL_FALL_VELOCITY),
+        let mut next_vel = Vec2::new(
+            (player.velocity.x + config.acceleration * dx).clamp(-move_speed, move_speed),
+            (player.velocity.y + dy).max(config.terminal_fall_velocity),
         );
+        next_vel *= 1. - config.liquid_drag * liquid;
+
+        if player.wall_sliding {
+            next_vel.y = next_vel.y.max(config.wall_slide_velocity);
+        }
 
-        let next_world_pos = tx.translation.truncate() + next_vel * UPDATE_RATE;
+        let mut next_world_pos = tx.translation.truncate() + next_vel * config.update_rate;
 
         let mut next_grid_pos = dims.world_to_grid(next_world_pos);
-        next_grid_pos.1 = next_grid_pos.1.clamp(0, dims.tex_h - 1);
-
-        // check the player can move to the next position
-        // TODO move as far as possible if unable to move here rather than just stopping in place
-        if !can_move(
-            (next_grid_pos.0 as i32, next_grid_pos.1 as i32),
-            &mut map,
-            &particles,
-        ) {
-            // stop
-            player.velocity = Vec2::ZERO;
-            continue;
+        next_grid_pos.1 = next_grid_pos.1.clamp(0, dims.tex_h - hitbox.height);
+
+        // shove loose sand out of the way of the hitbox, anything that can't be
+        // moved and is packed down blocks the player below
+        let empty_colour = to_u8s(colours.background);
+        let step_x = (next_grid_pos.0 as i32 - pos.0 as i32).signum();
+
+        if step_x != 0 {
+            let front = if step_x > 0 {
+                (pos.0 + hitbox.width) as i32
+            } else {
+                pos.0 as i32 - 1
+            };
+
+            for dy in (0..hitbox.height).rev() {
+                let cell = (front, (pos.1 + dy) as i32);
+                push_sand(
+                    &mut map,
+                    &dims,
+                    cell,
+                    &[(step_x, 0), (step_x, 1)],
+                    empty_colour,
+                );
+            }
         }
 
-        pos.0 = next_grid_pos.0;
-        pos.1 = next_grid_pos.1;
+        if next_grid_pos.1 > pos.1 {
+            let top = (pos.1 + hitbox.height) as i32;
+
+            for dx in 0..hitbox.width {
+                let cell = ((pos.0 + dx) as i32, top);
+                push_sand(
+                    &mut map,
+                    &dims,
+                    cell,
+                    &[(0, 1), (-1, 1), (1, 1)],
+                    empty_colour,
+                );
+            }
+        }
+
+        // sweep horizontally then vertically, stopping at the last free cell on each
+        // axis so the player slides along walls and lands on thin platforms
+        let (x, blocked_x) = sweep(pos.0 as i32, next_grid_pos.0 as i32, |x| {
+            fits(
+                (x, pos.1 as i32),
+                (pos.0 as i32, pos.1 as i32),
+                hitbox,
+                &map,
+                &config,
+            )
+        });
+        let (y, blocked_y) = sweep(pos.1 as i32, next_grid_pos.1 as i32, |y| {
+            fits((x, y), (x, pos.1 as i32), hitbox, &map, &config)
+        });
+
+        // only stop the blocked part of the movement
+        let stopped_at = dims.grid_to_world(x as u32, y as u32);
+
+        if blocked_x {
+            next_vel.x = 0.;
+            next_world_pos.x = stopped_at.x;
+        }
+
+        if blocked_y {
+            next_vel.y = 0.;
+            next_world_pos.y = stopped_at.y;
+        }
+
+        pos.0 = x as u32;
+        pos.1 = y as u32;
         player.velocity = next_vel;
         tx.translation = next_world_pos.extend(0.0);
     }
 }
 
+/// Steps one cell at a time from `from` towards `to`, returning the last cell that
+/// could be moved into and whether the movement was blocked before reaching `to`
+fn sweep(from: i32, to: i32, can_move_to: impl Fn(i32) -> bool) -> (i32, bool) {
+    let step = (to - from).signum();
+    let mut current = from;
+
+    while current != to {
+        if !can_move_to(current + step) {
+            return (current, true);
+        }
+
+        current += step;
+    }
+
+    (current, false)
+}
+
 /// Returns true if the given position is above "on the ground",
-/// usually meaning the grid square below the passed grid square is occupied
+/// usually meaning a grid square below the hitbox is occupied
 /// by a barrier.
-fn is_grounded(
-    pos: (u32, u32),
-    map: &mut ResMut<Map>,
-    particles: &Query<(&Particle, Entity)>,
-) -> bool {
-    !can_move((pos.0 as i32, pos.1 as i32 - 1), map, particles)
+fn is_grounded(pos: (u32, u32), hitbox: &Hitbox, map: &Map, config: &PlayerConfig) -> bool {
+    (0..hitbox.width).any(|dx| !can_move(((pos.0 + dx) as i32, pos.1 as i32 - 1), map, config))
+}
+
+/// Returns -1 if there is a wall directly left of the hitbox, 1 if there is one
+/// directly to the right, or 0 if the player isn't touching a wall
+fn touching_wall(pos: (u32, u32), hitbox: &Hitbox, map: &Map, config: &PlayerConfig) -> i32 {
+    let blocked =
+        |x: i32| (0..hitbox.height).any(|dy| !can_move((x, (pos.1 + dy) as i32), map, config));
+
+    if blocked(pos.0 as i32 - 1) {
+        -1
+    } else if blocked((pos.0 + hitbox.width) as i32) {
+        1
+    } else {
+        0
+    }
+}
+
+/// The fraction of the hitbox's cells that contain liquid (slime or water) and sand particles
+fn submerged(pos: (u32, u32), hitbox: &Hitbox, map: &Map) -> (f32, f32) {
+    let mut liquid = 0;
+    let mut sand = 0;
+
+    for dx in 0..hitbox.width {
+        for dy in 0..hitbox.height {
+            match map.get(pos.0 + dx, pos.1 + dy).map(|p| p.particle_type) {
+                Some(ParticleType::Liquid) | Some(ParticleType::Water) => liquid += 1,
+                Some(ParticleType::Sand) => sand += 1,
+                _ => {}
+            }
+        }
+    }
+
+    let cells = (hitbox.width * hitbox.height) as f32;
+    (liquid as f32 / cells, sand as f32 / cells)
 }
 
-/// Checks if there is an obstacle at the given position + movement
-fn can_move(
-    target: (i32, i32),
-    map: &mut ResMut<Map>,
-    particles: &Query<(&Particle, Entity)>,
+/// Checks if every cell of the hitbox is free when placed at the given position.
+/// Cells the hitbox already covers at `from` are skipped, so the player can't get
+/// stuck if sand packs down around them.
+fn fits(
+    origin: (i32, i32),
+    from: (i32, i32),
+    hitbox: &Hitbox,
+    map: &Map,
+    config: &PlayerConfig,
 ) -> bool {
+    let (w, h) = (hitbox.width as i32, hitbox.height as i32);
+    let covered = |x: i32, y: i32| x >= from.0 && x < from.0 + w && y >= from.1 && y < from.1 + h;
+
+    (0..w).all(|dx| {
+        (0..h).all(|dy| {
+            let (x, y) = (origin.0 + dx, origin.1 + dy);
+            covered(x, y) || can_move((x, y), map, config)
+        })
+    })
+}
+
+/// Checks if there is an obstacle or packed sand at the given position + movement
+fn can_move(target: (i32, i32), map: &Map, config: &PlayerConfig) -> bool {
     if target.0 < 0 || target.1 < 0 {
         return false;
     }
 
-    if let Some(entity) = map.get(target.0 as u32, target.1 as u32) {
-        match particles.get(entity) {
-            Ok((particle, _)) => match particle.particle_type {
-                // can't move through an obsctacle
-                ParticleType::Obstacle => false,
-                // can move through others
-                _ => true,
-            },
-            // nothing found, ok to move
+    let (x, y) = (target.0 as u32, target.1 as u32);
+
+    match map.get(x, y) {
+        Some(particle) => match particle.particle_type {
+            // can't move through an obsctacle
+            ParticleType::Obstacle => false,
+            // sand piles can be stood on once they're packed down
+            ParticleType::Sand => !is_packed((x, y), map, config.packed_sand_depth),
+            // can move through others
             _ => true,
+        },
+        // no particle at the location
+        None => true,
+    }
+}
+
+/// Sand is packed when the cells below it are filled (or are the bottom of the map)
+fn is_packed(pos: (u32, u32), map: &Map, depth: u32) -> bool {
+    (1..=depth).all(|d| d > pos.1 || map.get(pos.0, pos.1 - d).is_some())
+}
+
+/// Tries to shove a sand grain at `cell` by each of the offsets in turn
+fn push_sand(
+    map: &mut Map,
+    dims: &Dims,
+    cell: (i32, i32),
+    offsets: &[(i32, i32)],
+    empty_colour: [u8; 3],
+) {
+    if cell.0 < 0 || cell.1 < 0 {
+        return;
+    }
+
+    let from = (cell.0 as u32, cell.1 as u32);
+    match map.get(from.0, from.1) {
+        Some(particle) if particle.particle_type == ParticleType::Sand => {}
+        _ => return,
+    }
+
+    for (ox, oy) in offsets {
+        let to = (cell.0 + ox, cell.1 + oy);
+        if to.0 >= 0
+            && to.1 >= 0
+            && map.move_particle(dims, from, (to.0 as u32, to.1 as u32), empty_colour)
+        {
+            return;
         }
-    } else {
-        // no entity at the location
-        true
     }
 }
@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+use sf_core::{dims::Dims, Hitbox, Player};
+
+/// How far the player can move from the middle of the screen before the camera
+/// follows, as a fraction of the visible area
+const DEAD_ZONE: (f32, f32) = (0.15, 0.2);
+
+/// How quickly the camera catches up with the player, higher is snappier
+const SMOOTHING: f32 = 6.;
+
+/// Scrolls the camera to keep the player in view on levels bigger than the screen
+pub fn follow_camera(
+    time: Res<Time>,
+    mut dims: ResMut<Dims>,
+    players: Query<(&Transform, &Hitbox), With<Player>>,
+) {
+    let (tx, hitbox) = match players.iter().next() {
+        Some(player) => player,
+        None => return,
+    };
+
+    let focus = (tx.translation + hitbox.sprite_offset(&dims)).truncate();
+    let dead_zone = dims.visible_size() * Vec2::new(DEAD_ZONE.0, DEAD_ZONE.1);
+
+    // only move far enough to bring the player back to the edge of the dead zone
+    let offset = focus - dims.camera;
+    let target = dims.camera + offset - offset.max(-dead_zone).min(dead_zone);
+
+    let t = 1. - (-SMOOTHING * time.delta_seconds()).exp();
+    let next = dims.clamp_camera(dims.camera + (target - dims.camera) * t);
+
+    if next.distance_squared(dims.camera) > f32::EPSILON {
+        dims.camera = next;
+    }
+}
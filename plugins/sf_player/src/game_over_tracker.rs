@@ -1,19 +1,26 @@
 use bevy::prelude::*;
-use sf_core::{AudioState, GameOver, GameState, LightingTarget, Player, Position};
+use sf_core::{
+    ghost::GhostRuns, sim::SimClock, AudioState, GameOver, GameState, LightingTarget, Player,
+    PlayerState, Position,
+};
 
 pub fn game_over_tracker(
     mut commands: Commands,
+    clock: Res<SimClock>,
     mut state: ResMut<State<GameState>>,
     asset_server: Res<AssetServer>,
     audio_state: Res<AudioState>,
     audio: Res<bevy_kira_audio::Audio>,
-    players: Query<(&Position, &LightingTarget, &Player)>,
+    mut ghosts: ResMut<GhostRuns>,
+    mut players: Query<(&Position, &LightingTarget, &mut Player)>,
 ) {
-    let (pos, light, player) = players.single().expect("Should have a player");
+    let t = clock.seconds();
+    let (pos, light, mut player) = players.single_mut().expect("Should have a player");
 
     // check if the light has expired
     if light.lighting_strength == 0 {
         println!("DEFEAT");
+        player.set_state(PlayerState::Dead, t);
         commands.spawn().insert(GameOver);
         state.set(GameState::GameOver).unwrap();
         audio.play_in_channel(asset_server.load("sounds/death.ogg"), &audio_state.channel);
@@ -23,6 +30,7 @@ pub fn game_over_tracker(
     if pos.1 <= 0 {
         if player.slime_target == 0 {
             println!("VICTORY");
+            ghosts.finish();
             audio.play_in_channel(
                 asset_server.load("sounds/victory.ogg"),
                 &audio_state.channel,
@@ -30,6 +38,7 @@ pub fn game_over_tracker(
             state.set(GameState::Loading).unwrap();
         } else {
             println!("DEFEAT");
+            player.set_state(PlayerState::Dead, t);
             commands.spawn().insert(GameOver);
             state.set(GameState::GameOver).unwrap();
             audio.play_in_channel(asset_server.load("sounds/death.ogg"), &audio_state.channel);
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use sf_core::{
+    dims::Dims,
+    ghost::{Ghost, GhostRuns},
+    sim::SimClock,
+    Player, Position,
+};
+
+/// Remembers where the player is on each tick, in case this is their best run
+pub fn record_ghost(
+    clock: Res<SimClock>,
+    mut ghosts: ResMut<GhostRuns>,
+    players: Query<&Position, With<Player>>,
+) {
+    if !clock.ticked() {
+        return;
+    }
+
+    for pos in players.iter() {
+        ghosts.record((pos.0, pos.1));
+    }
+}
+
+/// Moves the ghost along its path, it waits at the end once it has finished
+pub fn move_ghost(
+    clock: Res<SimClock>,
+    dims: Res<Dims>,
+    mut ghosts: Query<(&Ghost, &mut Transform, &Children)>,
+    mut sprites: Query<&mut TextureAtlasSprite>,
+) {
+    if !clock.ticked() {
+        return;
+    }
+
+    // the path holds where the player was at the end of each tick, starting with tick 1
+    let tick = clock.tick().saturating_sub(1) as usize;
+
+    for (ghost, mut tx, children) in ghosts.iter_mut() {
+        let last = ghost.path.len() - 1;
+        let pos = ghost.path[tick.min(last)];
+        let prev = ghost.path[tick.saturating_sub(1).min(last)];
+
+        let world = dims.grid_to_world(pos.0, pos.1);
+        tx.translation.x = world.x;
+        tx.translation.y = world.y;
+
+        // face the way the ghost is heading
+        if pos.0 != prev.0 {
+            for child in children.iter() {
+                if let Ok(mut sprite) = sprites.get_mut(*child) {
+                    sprite.flip_x = pos.0 < prev.0;
+                }
+            }
+        }
+    }
+}
@@ -8,16 +8,22 @@ use sf_core::{
 
 mod animate_player;
 mod calculate_player_movement;
+mod follow_camera;
 mod game_over_tracker;
+mod ghost;
 mod lighting_decay;
 mod player_sink;
+mod player_state;
 mod spawn_player;
 mod update_player_ui;
 
 use animate_player::animate_player;
 use calculate_player_movement::calculate_player_movement;
+use follow_camera::follow_camera;
+use ghost::{move_ghost, record_ghost};
 use lighting_decay::lighting_decay;
 use player_sink::player_sink;
+use player_state::update_player_state;
 use spawn_player::spawn_player;
 
 pub struct PlayerPlugin;
@@ -39,9 +45,33 @@ impl Plugin for PlayerPlugin {
                     calculate_player_movement
                         .system()
                         .label("calculate_player_movement")
+                        .after("replay_input")
                         .before("game_over_tracker"),
                 )
-                .with_system(player_sink.system().after("calculate_player_movement"))
+                .with_system(
+                    player_sink
+                        .system()
+                        .label("player_sink")
+                        .after("calculate_player_movement"),
+                )
+                .with_system(
+                    record_ghost
+                        .system()
+                        .after("calculate_player_movement")
+                        .before("game_over_tracker"),
+                )
+                .with_system(move_ghost.system())
+                .with_system(
+                    follow_camera
+                        .system()
+                        .after("calculate_player_movement")
+                        .before("update_camera"),
+                )
+                .with_system(
+                    update_player_state
+                        .system()
+                        .after("calculate_player_movement"),
+                )
                 .with_system(
                     lighting_decay
                         .system()
@@ -1,13 +1,17 @@
 // handles periodically decaying a user's lighting
 
 use bevy::prelude::*;
-use sf_core::{LightingTarget, Player};
+use sf_core::{sim::SimClock, LightingTarget, Player};
 
-pub fn lighting_decay(time: Res<Time>, mut query: Query<&mut LightingTarget, With<Player>>) {
-    let t = time.seconds_since_startup();
+pub fn lighting_decay(clock: Res<SimClock>, mut query: Query<&mut LightingTarget, With<Player>>) {
+    if !clock.ticked() {
+        return;
+    }
+
+    let t = clock.seconds();
 
     for mut light in query.iter_mut() {
-        if light.lighting_strength == 0 || t < light.next_lighting_decay {
+        if light.lighting_strength == 0 || !clock.reached(light.next_lighting_decay) {
             continue;
         }
 
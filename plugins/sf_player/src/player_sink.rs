@@ -2,82 +2,82 @@ use bevy::prelude::*;
 use sf_core::{
     colors::{to_u8s, Colors},
     dims::Dims,
-    entity::Particle,
     map::Map,
-    AudioState, LightingTarget, Player, Position, StaticEntity,
+    player_config::PlayerConfig,
+    sim::SimClock,
+    AudioState, Hitbox, LightingTarget, Player, Position,
 };
 
-/// Removes the particles around a player
+/// Removes the collectable particles around a player
 pub fn player_sink(
-    mut commands: Commands,
     mut map: ResMut<Map>,
-    time: Res<Time>,
+    clock: Res<SimClock>,
     dims: Res<Dims>,
     asset_server: Res<AssetServer>,
     audio_state: Res<AudioState>,
     audio: Res<bevy_kira_audio::Audio>,
     colours: Res<Colors>,
-    mut players: Query<(&mut Player, &Position, &mut LightingTarget)>,
-    particles: Query<(&Particle, Entity), Without<StaticEntity>>,
+    config: Res<PlayerConfig>,
+    mut players: Query<(&mut Player, &Position, &Hitbox, &mut LightingTarget)>,
 ) {
-    let t = time.seconds_since_startup();
+    if !clock.ticked() {
+        return;
+    }
+
+    let t = clock.seconds();
 
-    'player_loop: for (mut player, pos, mut light) in players.iter_mut() {
+    'player_loop: for (mut player, pos, hitbox, mut light) in players.iter_mut() {
         let clear_colour = to_u8s(colours.background);
 
-        if t < player.next_sink || player.slime_target == 0 {
+        if !clock.reached(player.next_sink) || player.slime_target == 0 {
             continue;
         }
 
-        let x = pos.0;
-        let y = pos.1;
+        let x = pos.0 as i32;
+        let y = pos.1 as i32;
 
-        for &dx in &[0i32, -1, 1] {
-            for &dy in &[0i32, -1, 1] {
-                // check lower bounds
-                if (dx == -1 && x == 0) || (dy == -1 && y == 0) {
-                    continue;
-                }
-
-                // check upper bounds
-                if (dx == 1 && x >= dims.tex_w - 1) || (dy == 1 && y >= dims.tex_h - 1) {
+        // check the hitbox and a one cell border around it
+        for dx in -1..=hitbox.width as i32 {
+            for dy in -1..=hitbox.height as i32 {
+                // check bounds
+                if x + dx < 0
+                    || y + dy < 0
+                    || x + dx >= dims.tex_w as i32
+                    || y + dy >= dims.tex_h as i32
+                {
                     continue;
                 }
 
                 // construct the coordinates to check
-                let cx = (x as i32 + dx) as u32;
-                let cy = (y as i32 + dy) as u32;
+                let cx = (x + dx) as u32;
+                let cy = (y + dy) as u32;
 
-                // check if we have an entity at that point to consume
+                // check if we have a loose particle at that point to consume
                 match map.get(cx, cy) {
-                    Some(ent) => match particles.get(ent) {
-                        Ok((_, ent)) => {
-                            // remove the slime
-                            map.destroy_at(cx, cy, &dims, &clear_colour);
-                            commands.entity(ent).despawn();
+                    Some(particle) if particle.is_collectable() => {
+                        // remove the slime
+                        map.destroy_at(cx, cy, &dims, &clear_colour);
 
-                            // play a sound, this could get awful
-                            audio.play_in_channel(
-                                asset_server.load("sounds/pickup.ogg"),
-                                &audio_state.channel,
-                            );
+                        // play a sound, this could get awful
+                        audio.play_in_channel(
+                            asset_server.load("sounds/pickup.ogg"),
+                            &audio_state.channel,
+                        );
 
-                            // increment the player lighting
-                            player.slime_target -= 1;
+                        // increment the player lighting
+                        player.slime_target -= 1;
 
-                            if player.slime_target % light.light_growth_rate == 0 {
-                                light.lighting_strength = (light.lighting_strength + 1)
-                                    .clamp(0, light.max_light_strength);
-                            }
+                        if player.slime_target % light.light_growth_rate == 0 {
+                            light.lighting_strength =
+                                (light.lighting_strength + 1).clamp(0, light.max_light_strength);
+                        }
 
-                            player.next_sink = t + player.sink_rate;
+                        player.next_sink = t + config.sink_rate;
 
-                            // only sink one particle
-                            continue 'player_loop;
-                        }
-                        Err(_) => {}
-                    },
-                    None => {}
+                        // only sink one particle
+                        continue 'player_loop;
+                    }
+                    _ => {}
                 }
             }
         }
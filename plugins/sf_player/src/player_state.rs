@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use sf_core::{sim::SimClock, Player, PlayerState};
+
+/// Horizontal speed below which the player counts as standing still
+const RUN_THRESHOLD: f32 = 10.;
+
+/// How long the player stays in the landing state after touching down
+const LANDING_TIME: f64 = 0.15;
+
+/// Works out what the player is doing from how they're moving
+pub fn update_player_state(clock: Res<SimClock>, mut players: Query<&mut Player>) {
+    if !clock.ticked() {
+        return;
+    }
+
+    let t = clock.seconds();
+
+    for mut player in players.iter_mut() {
+        if player.state == PlayerState::Dead {
+            continue;
+        }
+
+        // face the way the player is moving, or towards the wall they're sliding down
+        if player.wall_sliding {
+            player.facing_left = player.wall_side < 0;
+        } else if player.velocity.x > RUN_THRESHOLD {
+            player.facing_left = false;
+        } else if player.velocity.x < -RUN_THRESHOLD {
+            player.facing_left = true;
+        }
+
+        let was_airborne = matches!(
+            player.state,
+            PlayerState::Jumping | PlayerState::Falling | PlayerState::WallSliding
+        );
+        let still_landing =
+            player.state == PlayerState::Landing && t - player.state_started < LANDING_TIME;
+
+        let next = if player.wall_sliding {
+            PlayerState::WallSliding
+        } else if !player.is_grounded {
+            if player.velocity.y > 0. {
+                PlayerState::Jumping
+            } else {
+                PlayerState::Falling
+            }
+        } else if was_airborne || still_landing {
+            PlayerState::Landing
+        } else if player.velocity.x.abs() > RUN_THRESHOLD {
+            PlayerState::Running
+        } else {
+            PlayerState::Idle
+        };
+
+        player.set_state(next, t);
+    }
+}
@@ -1,17 +1,26 @@
 use bevy::prelude::*;
-use sf_core::{dims::Dims, LightingTarget, Player, Position};
+use sf_core::{
+    dims::Dims, ghost::GhostRuns, player_config::PlayerConfig, LightingTarget, Player, PlayerState,
+    Position,
+};
 
 pub fn spawn_player(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     dims: Res<Dims>,
+    config: Res<PlayerConfig>,
+    mut ghosts: ResMut<GhostRuns>,
 ) {
     let texture_handle = asset_server.load("character.png");
-    let texture_atlas = TextureAtlas::from_grid(texture_handle, Vec2::new(16., 32.0), 5, 1);
+    let texture_atlas = TextureAtlas::from_grid(texture_handle, Vec2::new(16., 32.0), 7, 1);
     let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    ghosts.atlas = texture_atlas_handle.clone();
 
     let player_pos = (10, 50);
+    let hitbox = config.hitbox();
+    let sprite_offset = hitbox.sprite_offset(&dims);
+
     let player_tx =
         Transform::from_translation(dims.grid_to_world(player_pos.0, player_pos.1).extend(0.));
 
@@ -26,25 +35,32 @@ pub fn spawn_player(
         })
         .insert(player_tx.clone())
         .insert(GlobalTransform::from_translation(Vec3::ZERO))
-        .insert(Timer::from_seconds(0.5, true))
         .insert(Position(player_pos.0, player_pos.1))
+        .insert(hitbox)
         .insert(Player {
             velocity: Vec2::ZERO,
-            move_speed: 200.,
             is_grounded: false,
             frames_since_jumped: 0,
             jump_cooldown: 0,
             did_jump: false,
+            jump_held: false,
+            last_grounded: f64::NEG_INFINITY,
+            last_jump_pressed: f64::NEG_INFINITY,
+            wall_side: 0,
+            wall_sliding: false,
             next_update: 0.,
 
             slime_target: 0,
-            sink_rate: 1. / 10., // 10 per second
             next_sink: 0.,
+
+            state: PlayerState::Idle,
+            state_started: 0.,
+            facing_left: false,
         })
         .with_children(|parent| {
             parent.spawn().insert_bundle(SpriteSheetBundle {
                 texture_atlas: texture_atlas_handle,
-                transform: Transform::from_translation(Vec3::new(0.0, 16.0, 0.0)),
+                transform: Transform::from_translation(sprite_offset),
                 ..Default::default()
             });
         });
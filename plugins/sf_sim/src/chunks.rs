@@ -0,0 +1,76 @@
+/// The width and height of a chunk in cells
+pub const CHUNK_SIZE: u32 = 16;
+
+/// Tracks which parts of the grid might have something to do. A chunk goes to sleep
+/// after a step where nothing in or around it changed, and is woken when a cell near
+/// it is changed by a move, a spawn or a removal.
+pub struct Chunks {
+    w: u32,
+    h: u32,
+    /// chunks to step this tick
+    awake: Vec<bool>,
+    /// chunks to step next tick
+    wake_next: Vec<bool>,
+}
+
+impl Chunks {
+    /// Creates (sleeping) chunks to cover a grid of the given size
+    pub fn new(grid_w: u32, grid_h: u32) -> Self {
+        let w = grid_w.div_ceil(CHUNK_SIZE);
+        let h = grid_h.div_ceil(CHUNK_SIZE);
+
+        Chunks {
+            w,
+            h,
+            awake: vec![false; (w * h) as usize],
+            wake_next: vec![false; (w * h) as usize],
+        }
+    }
+
+    /// Moves on to the next tick, the chunks woken during the last one are now awake
+    pub fn begin_step(&mut self) {
+        std::mem::swap(&mut self.awake, &mut self.wake_next);
+        self.wake_next.iter_mut().for_each(|awake| *awake = false);
+    }
+
+    /// Returns true if the chunk containing the given cell should be stepped
+    pub fn is_awake(&self, x: u32, y: u32) -> bool {
+        self.awake[self.to_idx(x / CHUNK_SIZE, y / CHUNK_SIZE)]
+    }
+
+    /// Wakes every chunk within `radius` cells of the given cell, for the rest of
+    /// this tick and the next one
+    pub fn wake_around(&mut self, x: u32, y: u32, radius: u32) {
+        if self.awake.is_empty() {
+            return;
+        }
+
+        let min_x = x.saturating_sub(radius) / CHUNK_SIZE;
+        let min_y = y.saturating_sub(radius) / CHUNK_SIZE;
+        let max_x = ((x + radius) / CHUNK_SIZE).min(self.w - 1);
+        let max_y = ((y + radius) / CHUNK_SIZE).min(self.h - 1);
+
+        for cy in min_y..=max_y {
+            for cx in min_x..=max_x {
+                let idx = self.to_idx(cx, cy);
+                self.awake[idx] = true;
+                self.wake_next[idx] = true;
+            }
+        }
+    }
+
+    /// Puts every chunk to sleep
+    pub fn sleep_all(&mut self) {
+        self.awake.iter_mut().for_each(|awake| *awake = false);
+        self.wake_next.iter_mut().for_each(|awake| *awake = false);
+    }
+
+    /// The number of chunks that will be stepped next tick
+    pub fn awake_count(&self) -> usize {
+        self.wake_next.iter().filter(|&&awake| awake).count()
+    }
+
+    fn to_idx(&self, cx: u32, cy: u32) -> usize {
+        (cy * self.w + cx) as usize
+    }
+}
@@ -0,0 +1,17 @@
+use crate::particle::Particle;
+
+/// Anything the particle rules can be run against
+pub trait Grid {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+
+    /// Returns the particle in the given cell, if the cell is inside the grid and occupied
+    fn particle_at(&self, x: i32, y: i32) -> Option<&Particle>;
+
+    /// Returns true if the given cell is inside the grid and unoccupied
+    fn is_free(&self, x: i32, y: i32) -> bool;
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.width() && (y as u32) < self.height()
+    }
+}
@@ -0,0 +1,12 @@
+//! A headless falling sand simulation. Nothing in here depends on bevy, so the
+//! simulation can be stepped from tests, tools or CI without a window or GPU.
+
+pub mod chunks;
+pub mod grid;
+pub mod particle;
+pub mod rules;
+pub mod simulation;
+
+pub use grid::Grid;
+pub use particle::{Particle, ParticleType};
+pub use simulation::{Move, Simulation};
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticleType {
+    Obstacle,
+    // Trap,
+    Sand,
+    /// slime, which the player collects
+    Liquid,
+    /// spreads out quickly and is pushed aside by anything heavier
+    Water,
+}
+
+impl ParticleType {
+    /// How heavy the particle is, heavier particles sink through lighter water
+    pub fn density(&self) -> u8 {
+        match self {
+            ParticleType::Obstacle => u8::MAX,
+            ParticleType::Sand => 3,
+            ParticleType::Liquid => 2,
+            ParticleType::Water => 1,
+        }
+    }
+
+    /// How many cells sideways the particle can flow in a tick when it can't fall
+    pub fn spread(&self) -> u32 {
+        match self {
+            ParticleType::Liquid => 1,
+            ParticleType::Water => 4,
+            _ => 0,
+        }
+    }
+}
+
+/// The state of a single grain in the simulation. The position of the particle
+/// is the grid cell it occupies, so it isn't stored here.
+#[derive(Debug, Copy, Clone)]
+pub struct Particle {
+    pub particle_type: ParticleType,
+    pub vel: (i32, i32),
+    pub color: [u8; 3],
+    pub is_left_first: bool,
+    /// glowing particles light up the cells around them
+    pub emissive: bool,
+}
+
+impl Particle {
+    pub fn is_static(&self) -> bool {
+        matches!(self.particle_type, ParticleType::Obstacle)
+    }
+
+    /// Loose particles other than water can be picked up by the player
+    pub fn is_collectable(&self) -> bool {
+        !self.is_static() && self.particle_type != ParticleType::Water
+    }
+
+    /// Returns true if this particle can fall into a cell held by `other`, swapping
+    /// places with it. Only water is light enough to be pushed aside.
+    pub fn displaces(&self, other: &Particle) -> bool {
+        other.particle_type == ParticleType::Water
+            && self.particle_type.density() > other.particle_type.density()
+    }
+
+    /// The furthest this particle can look or move from its cell in a tick
+    pub fn reach(&self) -> u32 {
+        let speed = self.vel.0.abs().max(self.vel.1.abs()) as u32;
+        speed + self.particle_type.spread()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle(particle_type: ParticleType) -> Particle {
+        Particle {
+            particle_type,
+            vel: (0, -1),
+            color: [0; 3],
+            is_left_first: true,
+            emissive: false,
+        }
+    }
+
+    #[test]
+    fn water_and_walls_cannot_be_collected() {
+        assert!(!particle(ParticleType::Water).is_collectable());
+        assert!(!particle(ParticleType::Obstacle).is_collectable());
+        assert!(particle(ParticleType::Sand).is_collectable());
+        assert!(particle(ParticleType::Liquid).is_collectable());
+    }
+
+    #[test]
+    fn only_heavier_particles_displace_water() {
+        let water = particle(ParticleType::Water);
+        assert!(particle(ParticleType::Sand).displaces(&water));
+        assert!(particle(ParticleType::Liquid).displaces(&water));
+        assert!(!water.displaces(&water));
+        assert!(!particle(ParticleType::Sand).displaces(&particle(ParticleType::Liquid)));
+    }
+}
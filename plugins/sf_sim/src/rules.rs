@@ -0,0 +1,261 @@
+use crate::{grid::Grid, particle::Particle};
+
+/// Works out where a particle at `pos` wants to move to this tick.
+/// Returns None if the particle should stay where it is. The target may hold
+/// water that the particle is heavy enough to swap places with.
+pub fn next_position<G: Grid>(
+    grid: &G,
+    pos: (u32, u32),
+    particle: &Particle,
+) -> Option<(u32, u32)> {
+    // walls don't move, and particles on the floor of the level stay put
+    if particle.is_static() || pos.1 == 0 {
+        return None;
+    }
+
+    let x = pos.0 as i32 + particle.vel.0;
+    let y = pos.1 as i32 + particle.vel.1;
+
+    // falling can push lighter particles out of the way
+    let can_fall_into = |x: i32, y: i32| {
+        grid.is_free(x, y)
+            || matches!(grid.particle_at(x, y), Some(other) if particle.displaces(other))
+    };
+
+    // if the square directly below is free, fall into it
+    if can_fall_into(x, y) {
+        return Some((x as u32, y as u32));
+    }
+
+    // otherwise try the diagonals, starting with the preferred side
+    let sides = if particle.is_left_first {
+        [-1, 1]
+    } else {
+        [1, -1]
+    };
+
+    for &dx in &sides {
+        if can_fall_into(x + dx, y) {
+            return Some(((x + dx) as u32, y as u32));
+        }
+    }
+
+    // liquids spread out sideways if they can't fall, flowing as far as they can
+    // in a tick until they hit something
+    let spread = particle.particle_type.spread() as i32;
+    for &dx in &sides {
+        let furthest = (1..=spread)
+            .take_while(|step| grid.is_free(x + dx * step, y + 1))
+            .last();
+
+        if let Some(step) = furthest {
+            return Some(((x + dx * step) as u32, (y + 1) as u32));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Particle, ParticleType, Simulation};
+
+    fn particle(particle_type: ParticleType, is_left_first: bool) -> Particle {
+        Particle {
+            particle_type,
+            vel: (0, -1),
+            color: [0; 3],
+            is_left_first,
+            emissive: false,
+        }
+    }
+
+    fn sand() -> Particle {
+        particle(ParticleType::Sand, true)
+    }
+
+    fn wall() -> Particle {
+        particle(ParticleType::Obstacle, true)
+    }
+
+    fn is_type(sim: &Simulation, x: u32, y: u32, particle_type: ParticleType) -> bool {
+        sim.get(x, y).map(|p| p.particle_type) == Some(particle_type)
+    }
+
+    #[test]
+    fn sand_falls_straight_down() {
+        let mut sim = Simulation::new(5, 5);
+        sim.spawn(2, 4, sand());
+
+        sim.step();
+        assert!(sim.get(2, 4).is_none());
+        assert!(is_type(&sim, 2, 3, ParticleType::Sand));
+
+        sim.step_n(10);
+        assert!(is_type(&sim, 2, 0, ParticleType::Sand));
+    }
+
+    #[test]
+    fn sand_slides_down_the_preferred_diagonal_first() {
+        let mut sim = Simulation::new(5, 5);
+        sim.spawn(2, 1, wall());
+        sim.spawn(2, 2, particle(ParticleType::Sand, true));
+
+        sim.step();
+        assert!(sim.get(2, 2).is_none());
+        assert!(is_type(&sim, 1, 1, ParticleType::Sand));
+
+        let mut sim = Simulation::new(5, 5);
+        sim.spawn(2, 1, wall());
+        sim.spawn(2, 2, particle(ParticleType::Sand, false));
+
+        sim.step();
+        assert!(is_type(&sim, 3, 1, ParticleType::Sand));
+    }
+
+    #[test]
+    fn sand_takes_the_other_diagonal_if_the_preferred_one_is_blocked() {
+        let mut sim = Simulation::new(5, 5);
+        sim.spawn(1, 1, wall());
+        sim.spawn(2, 1, wall());
+        sim.spawn(2, 2, particle(ParticleType::Sand, true));
+
+        sim.step();
+        assert!(is_type(&sim, 3, 1, ParticleType::Sand));
+    }
+
+    #[test]
+    fn sand_stops_when_the_diagonals_are_blocked() {
+        let mut sim = Simulation::new(5, 5);
+        for x in 1..4 {
+            sim.spawn(x, 1, wall());
+        }
+        sim.spawn(2, 2, sand());
+
+        assert!(sim.step().is_empty());
+        assert!(is_type(&sim, 2, 2, ParticleType::Sand));
+    }
+
+    #[test]
+    fn liquid_spreads_sideways_when_blocked() {
+        let mut sim = Simulation::new(5, 5);
+        for x in 1..4 {
+            sim.spawn(x, 1, wall());
+        }
+        sim.spawn(2, 2, particle(ParticleType::Liquid, true));
+
+        sim.step();
+        assert!(sim.get(2, 2).is_none());
+        assert!(is_type(&sim, 1, 2, ParticleType::Liquid));
+
+        let mut sim = Simulation::new(5, 5);
+        for x in 1..4 {
+            sim.spawn(x, 1, wall());
+        }
+        sim.spawn(2, 2, particle(ParticleType::Liquid, false));
+
+        sim.step();
+        assert!(is_type(&sim, 3, 2, ParticleType::Liquid));
+    }
+
+    #[test]
+    fn obstacles_never_move() {
+        let mut sim = Simulation::new(5, 5);
+        let mut falling = wall();
+        falling.vel = (1, -2);
+        sim.spawn(2, 4, falling);
+
+        assert!(sim.step().is_empty());
+        sim.step_n(10);
+        assert!(is_type(&sim, 2, 4, ParticleType::Obstacle));
+    }
+
+    #[test]
+    fn particles_on_the_floor_stay_put() {
+        let mut sim = Simulation::new(5, 5);
+        sim.spawn(2, 0, sand());
+        sim.spawn(0, 0, particle(ParticleType::Liquid, true));
+
+        assert!(sim.step().is_empty());
+        assert!(is_type(&sim, 2, 0, ParticleType::Sand));
+        assert!(is_type(&sim, 0, 0, ParticleType::Liquid));
+    }
+
+    #[test]
+    fn spawning_outside_the_grid_fails() {
+        let mut sim = Simulation::new(5, 5);
+        assert!(!sim.spawn(5, 0, sand()));
+        assert!(!sim.spawn(0, 5, sand()));
+        assert!(sim.spawn(4, 4, sand()));
+        assert!(!sim.spawn(4, 4, sand()));
+    }
+
+    #[test]
+    fn sand_swaps_places_with_water() {
+        let mut sim = Simulation::new(5, 5);
+        sim.spawn(2, 0, particle(ParticleType::Water, true));
+        sim.spawn(2, 1, sand());
+
+        let moves = sim.step();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].from, (2, 1));
+        assert_eq!(moves[0].to, (2, 0));
+        assert!(moves[0].displaced);
+
+        assert!(is_type(&sim, 2, 0, ParticleType::Sand));
+        assert!(is_type(&sim, 2, 1, ParticleType::Water));
+    }
+
+    #[test]
+    fn slime_sinks_through_water() {
+        let mut sim = Simulation::new(3, 6);
+        for y in 0..6 {
+            sim.spawn(0, y, wall());
+            sim.spawn(2, y, wall());
+        }
+        for y in 0..5 {
+            sim.spawn(1, y, particle(ParticleType::Water, true));
+        }
+        sim.spawn(1, 5, particle(ParticleType::Liquid, true));
+
+        sim.step_n(10);
+        assert!(is_type(&sim, 1, 0, ParticleType::Liquid));
+        assert!((1..6).all(|y| is_type(&sim, 1, y, ParticleType::Water)));
+    }
+
+    #[test]
+    fn water_does_not_displace_water() {
+        let mut sim = Simulation::new(3, 3);
+        for x in 0..3 {
+            sim.spawn(x, 0, wall());
+        }
+        sim.spawn(0, 1, wall());
+        sim.spawn(2, 1, wall());
+        sim.spawn(1, 1, particle(ParticleType::Water, true));
+        sim.spawn(1, 2, particle(ParticleType::Water, true));
+
+        // the top drop would have to swap with the one below, so it flows sideways instead
+        let moves = sim.step();
+        assert!(moves.iter().all(|mv| !mv.displaced));
+        assert!(is_type(&sim, 1, 1, ParticleType::Water));
+        assert!(sim.get(1, 2).is_none());
+    }
+
+    #[test]
+    fn water_flows_four_cells_and_stops_at_obstacles() {
+        let mut sim = Simulation::new(12, 3);
+        for x in 0..12 {
+            sim.spawn(x, 0, wall());
+        }
+        sim.spawn(7, 1, wall());
+        sim.spawn(1, 1, particle(ParticleType::Water, false));
+
+        sim.step();
+        assert!(is_type(&sim, 5, 1, ParticleType::Water));
+
+        // only two cells left before the wall
+        sim.step();
+        assert!(is_type(&sim, 6, 1, ParticleType::Water));
+        assert!(is_type(&sim, 7, 1, ParticleType::Obstacle));
+    }
+}
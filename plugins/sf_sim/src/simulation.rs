@@ -0,0 +1,399 @@
+use crate::{
+    chunks::{Chunks, CHUNK_SIZE},
+    grid::Grid,
+    particle::Particle,
+    rules::next_position,
+};
+
+/// A particle moving from one cell to another during a step
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Move {
+    pub from: (u32, u32),
+    pub to: (u32, u32),
+    /// true if the particle pushed water out of `to`, which is now in `from`
+    pub displaced: bool,
+}
+
+/// A dense grid of particles that can be stepped without an App.
+/// (0, 0) is the bottom left of the grid. Only the chunks where something
+/// has changed recently are stepped, see `Chunks`.
+pub struct Simulation {
+    w: u32,
+    h: u32,
+    cells: Vec<Option<Particle>>,
+    tick: u64,
+    chunks: Chunks,
+    /// how far from a changed cell the rules might notice the change, which
+    /// grows with the furthest reaching particle spawned
+    wake_radius: u32,
+    /// cells moved into this step, so nothing moves twice
+    moved: Vec<bool>,
+}
+
+impl Simulation {
+    pub fn new(w: u32, h: u32) -> Self {
+        Simulation {
+            w,
+            h,
+            cells: vec![None; (w * h) as usize],
+            tick: 0,
+            chunks: Chunks::new(w, h),
+            wake_radius: 1,
+            moved: vec![false; (w * h) as usize],
+        }
+    }
+
+    /// The number of chunks that will be stepped next tick
+    pub fn awake_chunks(&self) -> usize {
+        self.chunks.awake_count()
+    }
+
+    /// The number of steps run so far
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<&Particle> {
+        if x >= self.w || y >= self.h {
+            return None;
+        }
+
+        self.cells[self.to_idx(x, y)].as_ref()
+    }
+
+    /// Places a particle in the given cell. Returns false if the cell is
+    /// occupied or outside the grid.
+    pub fn spawn(&mut self, x: u32, y: u32, particle: Particle) -> bool {
+        if !self.is_free(x as i32, y as i32) {
+            return false;
+        }
+
+        let idx = self.to_idx(x, y);
+        self.cells[idx] = Some(particle);
+
+        self.wake_radius = self.wake_radius.max(particle.reach() + 1);
+        self.chunks.wake_around(x, y, self.wake_radius);
+        true
+    }
+
+    /// Removes and returns the particle in the given cell
+    pub fn remove(&mut self, x: u32, y: u32) -> Option<Particle> {
+        if x >= self.w || y >= self.h {
+            return None;
+        }
+
+        let idx = self.to_idx(x, y);
+        let particle = self.cells[idx].take();
+        if particle.is_some() {
+            self.chunks.wake_around(x, y, self.wake_radius);
+        }
+
+        particle
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = None;
+        }
+
+        self.chunks.sleep_all();
+        self.wake_radius = 1;
+    }
+
+    /// Advances the simulation by one tick, returning the particles that moved
+    pub fn step(&mut self) -> Vec<Move> {
+        self.tick += 1;
+        self.chunks.begin_step();
+
+        let mut moves = vec![];
+
+        // run bottom to top so that falling columns move together
+        for y in 0..self.h {
+            for chunk_x in (0..self.w).step_by(CHUNK_SIZE as usize) {
+                if !self.chunks.is_awake(chunk_x, y) {
+                    continue;
+                }
+
+                for x in chunk_x..(chunk_x + CHUNK_SIZE).min(self.w) {
+                    if let Some(mv) = self.step_cell(x, y) {
+                        moves.push(mv);
+                    }
+                }
+            }
+        }
+
+        for mv in moves.iter() {
+            let idx = self.to_idx(mv.to.0, mv.to.1);
+            self.moved[idx] = false;
+        }
+
+        moves
+    }
+
+    /// Moves the particle in the given cell if it can, waking everything around
+    /// the cells it moved between
+    fn step_cell(&mut self, x: u32, y: u32) -> Option<Move> {
+        let idx = self.to_idx(x, y);
+        if self.moved[idx] {
+            return None;
+        }
+
+        let particle = self.cells[idx]?;
+        let next = next_position(self, (x, y), &particle)?;
+
+        let next_idx = self.to_idx(next.0, next.1);
+        let displaced = self.cells[next_idx].is_some();
+        self.cells.swap(idx, next_idx);
+        self.moved[next_idx] = true;
+
+        self.chunks.wake_around(x, y, self.wake_radius);
+        self.chunks.wake_around(next.0, next.1, self.wake_radius);
+
+        Some(Move {
+            from: (x, y),
+            to: next,
+            displaced,
+        })
+    }
+
+    /// Advances the simulation by `n` ticks
+    pub fn step_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    fn to_idx(&self, x: u32, y: u32) -> usize {
+        (y * self.w + x) as usize
+    }
+}
+
+impl Grid for Simulation {
+    fn width(&self) -> u32 {
+        self.w
+    }
+
+    fn height(&self) -> u32 {
+        self.h
+    }
+
+    fn particle_at(&self, x: i32, y: i32) -> Option<&Particle> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+
+        self.cells[self.to_idx(x as u32, y as u32)].as_ref()
+    }
+
+    fn is_free(&self, x: i32, y: i32) -> bool {
+        self.in_bounds(x, y) && self.cells[self.to_idx(x as u32, y as u32)].is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParticleType;
+
+    fn particle(particle_type: ParticleType, is_left_first: bool) -> Particle {
+        Particle {
+            particle_type,
+            vel: (0, -1),
+            color: [0; 3],
+            is_left_first,
+            emissive: false,
+        }
+    }
+
+    fn sand() -> Particle {
+        particle(ParticleType::Sand, true)
+    }
+
+    /// Drops a column of sand onto the floor and steps until it has settled
+    fn settled_pile(sim: &mut Simulation, x: u32, height: u32) {
+        for y in 0..height {
+            sim.spawn(x, y + 4, sand());
+        }
+
+        sim.step_n(100);
+    }
+
+    fn count(sim: &Simulation) -> usize {
+        let mut count = 0;
+        for y in 0..sim.h {
+            for x in 0..sim.w {
+                if sim.get(x, y).is_some() {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn settled_piles_go_to_sleep() {
+        let mut sim = Simulation::new(32, 32);
+        settled_pile(&mut sim, 8, 10);
+
+        assert_eq!(sim.awake_chunks(), 0);
+        assert!(sim.step().is_empty());
+        assert_eq!(count(&sim), 10);
+    }
+
+    #[test]
+    fn removing_a_grain_wakes_the_pile() {
+        let mut sim = Simulation::new(32, 32);
+        settled_pile(&mut sim, 8, 10);
+        assert_eq!(sim.awake_chunks(), 0);
+
+        // knock out the grain at the bottom of the middle of the pile
+        assert!(sim.remove(8, 0).is_some());
+        assert!(sim.awake_chunks() > 0);
+
+        sim.step_n(20);
+        assert!(sim.get(8, 0).is_some());
+        assert_eq!(count(&sim), 9);
+        assert_eq!(sim.awake_chunks(), 0);
+    }
+
+    #[test]
+    fn spawning_next_to_a_pile_wakes_it() {
+        let mut sim = Simulation::new(32, 32);
+        settled_pile(&mut sim, 8, 10);
+        let top = (0..32).rev().find(|&y| sim.get(8, y).is_some()).unwrap();
+
+        sim.spawn(8, top + 2, sand());
+        assert!(sim.awake_chunks() > 0);
+
+        sim.step_n(20);
+        assert!(sim.get(8, top + 2).is_none());
+        assert_eq!(count(&sim), 11);
+        assert_eq!(sim.awake_chunks(), 0);
+    }
+
+    #[test]
+    fn particles_wake_the_chunks_they_fall_into() {
+        let h = CHUNK_SIZE * 3;
+        let mut sim = Simulation::new(CHUNK_SIZE * 2, h);
+        sim.spawn(CHUNK_SIZE - 1, h - 1, sand());
+
+        // one chunk down
+        sim.step_n(CHUNK_SIZE as usize);
+        assert!(sim.get(CHUNK_SIZE - 1, CHUNK_SIZE * 2 - 1).is_some());
+
+        sim.step_n(h as usize);
+        assert!(sim.get(CHUNK_SIZE - 1, 0).is_some());
+
+        // sliding off a wall into the chunk to the right
+        sim.spawn(CHUNK_SIZE - 1, 1, particle(ParticleType::Obstacle, true));
+        sim.spawn(CHUNK_SIZE - 2, 1, particle(ParticleType::Obstacle, true));
+        sim.spawn(CHUNK_SIZE - 1, 2, sand());
+        sim.step_n(5);
+
+        assert!(sim.get(CHUNK_SIZE, 0).is_some());
+    }
+
+    #[test]
+    fn fast_particles_widen_the_wake_radius() {
+        let mut sim = Simulation::new(CHUNK_SIZE, CHUNK_SIZE * 3);
+        assert_eq!(sim.wake_radius, 1);
+
+        let mut fast = sand();
+        fast.vel = (0, -4);
+        sim.spawn(3, 40, fast);
+        assert_eq!(sim.wake_radius, 5);
+
+        // it falls four cells a tick, through the sleeping chunks below and on to the floor
+        sim.step();
+        assert!(sim.get(3, 36).is_some());
+        sim.step_n(10);
+        assert!(sim.get(3, 0).is_some());
+    }
+
+    #[test]
+    fn water_widens_the_wake_radius() {
+        let mut sim = Simulation::new(CHUNK_SIZE * 2, CHUNK_SIZE);
+        assert_eq!(sim.wake_radius, 1);
+
+        // a ledge running from the left chunk into the right one, with a wall at the end
+        for x in 0..(CHUNK_SIZE * 2) {
+            sim.spawn(x, 0, particle(ParticleType::Obstacle, true));
+        }
+        sim.spawn(CHUNK_SIZE + 10, 1, particle(ParticleType::Obstacle, true));
+        sim.step();
+        assert_eq!(sim.awake_chunks(), 0);
+
+        let water = particle(ParticleType::Water, false);
+        sim.spawn(CHUNK_SIZE - 4, 1, water);
+        assert_eq!(sim.wake_radius, water.reach() + 1);
+        assert_eq!(sim.wake_radius, 6);
+
+        // the water flows 4 cells a tick, into the sleeping chunk and on until it hits the wall
+        sim.step();
+        assert!(sim.get(CHUNK_SIZE, 1).is_some());
+        sim.step_n(2);
+        assert!(sim.get(CHUNK_SIZE + 8, 1).is_some());
+        sim.step();
+        assert!(sim.get(CHUNK_SIZE + 9, 1).is_some());
+    }
+
+    /// What a cell holds, for comparing grids
+    type Cell = Option<(ParticleType, (i32, i32), bool)>;
+
+    /// Plays a level headlessly, changing the grid in the same order as the game's
+    /// systems each frame: the player digs, a sink drains, the grid steps and a
+    /// spawner emits. Inputs come from a simple generator seeded with `seed`.
+    fn play(seed: u64, frames: usize) -> Vec<Cell> {
+        let mut rng = seed;
+        let mut next = move || {
+            rng = rng
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (rng >> 33) as u32
+        };
+
+        let mut sim = Simulation::new(CHUNK_SIZE * 2, CHUNK_SIZE * 2);
+        let mut player_x = CHUNK_SIZE;
+
+        for frame in 0..frames {
+            // calculate_player_movement then player_sink
+            player_x = match next() % 3 {
+                0 => player_x.saturating_sub(1),
+                1 => (player_x + 1).min(sim.w - 1),
+                _ => player_x,
+            };
+            sim.remove(player_x, 4);
+
+            // sink_consumption
+            sim.remove(2, 0);
+
+            // sand_updater
+            sim.step();
+
+            // spawner_emission
+            let particle_type = if frame % 2 == 0 {
+                ParticleType::Sand
+            } else {
+                ParticleType::Water
+            };
+            sim.spawn(
+                next() % sim.w,
+                sim.h - 1,
+                particle(particle_type, next() % 2 == 0),
+            );
+        }
+
+        sim.cells
+            .iter()
+            .map(|cell| cell.map(|p| (p.particle_type, p.vel, p.is_left_first)))
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_and_inputs_give_the_same_grid() {
+        let first = play(42, 400);
+        assert!(first.iter().any(Option::is_some));
+        assert_eq!(first, play(42, 400));
+    }
+}
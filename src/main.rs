@@ -4,26 +4,32 @@
 #[cfg(target_arch = "wasm32")]
 use bevy_webgl2;
 
+use bevy::DefaultPlugins;
 use bevy::{
     prelude::*,
-    render::{pipeline::PipelineDescriptor, pipeline::RenderPipeline, texture::TextureFormat},
+    render::{pipeline::PipelineDescriptor, pipeline::RenderPipeline},
 };
-use bevy::{render::texture::Extent3d, DefaultPlugins};
 use bevy_kira_audio::{AudioChannel, AudioPlugin};
 
 use sf_core::{
-    colors::Colors, dims::Dims, input::InputState, levels::NextLevel, map::Map,
-    render::render_pipeline::LightSource, AudioState, CorePlugin, GameState, MainCamera,
-    MainTexture,
+    colors::Colors,
+    dims::{Dims, VIEW_SIZE},
+    input::InputState,
+    levels::NextLevel,
+    map::Map,
+    render::{
+        lights::{light_texture, LightingSettings},
+        render_pipeline::LightSource,
+    },
+    sim::SimRng,
+    AudioState, CorePlugin, GameState, MainCamera, MainTexture,
 };
+use sf_editor::EditorPlugin;
 use sf_game::GamePlugin;
 use sf_player::PlayerPlugin;
 
-const WINDOW_WIDTH: u32 = 1200;
-const WINDOW_HEIGHT: u32 = 800;
-
+/// World units per grid cell, the window starts out showing one pixel per unit
 const TEXTURE_STRIDE: u32 = 8;
-const TEXTURE_TYPE: TextureFormat = TextureFormat::Rgba8Unorm;
 
 fn main() {
     set_panic_hook();
@@ -36,11 +42,14 @@ fn main() {
         .insert_resource(ClearColor(colors.background))
         .insert_resource(colors)
         .insert_resource(InputState::default())
+        .insert_resource(SimRng::new(
+            arg_value("--seed").and_then(|seed| seed.parse().ok()),
+        ))
         .insert_resource(WindowDescriptor {
-            width: WINDOW_WIDTH as f32,
-            height: WINDOW_HEIGHT as f32,
+            width: (VIEW_SIZE.0 * TEXTURE_STRIDE) as f32,
+            height: (VIEW_SIZE.1 * TEXTURE_STRIDE) as f32,
             title: "LD48: Slimefall".to_string(),
-            resizable: false,
+            resizable: true,
             ..Default::default()
         })
         .add_plugins(DefaultPlugins)
@@ -51,12 +60,24 @@ fn main() {
         .add_plugin(bevy::diagnostic::EntityCountDiagnosticsPlugin::default())
         .add_plugin(PlayerPlugin)
         .add_plugin(GamePlugin)
+        .add_plugin(EditorPlugin)
         .add_startup_system(setup.system())
         .insert_resource(NextLevel(1));
 
     #[cfg(target_arch = "wasm32")]
     app.add_plugin(bevy_webgl2::WebGL2Plugin);
 
+    // play back a recording saved with F8 instead of reading the keyboard
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = arg_value("--replay") {
+        match sf_core::replay::Recording::load(&path) {
+            Ok(recording) => {
+                app.insert_resource(sf_core::replay::InputLog::replay(recording));
+            }
+            Err(e) => println!("Unable to load replay from {}: {}", path, e),
+        }
+    }
+
     app.run();
 }
 
@@ -69,7 +90,12 @@ fn setup(
     mut pipelines: ResMut<Assets<PipelineDescriptor>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut textures: ResMut<Assets<Texture>>,
+    lighting: Res<LightingSettings>,
 ) {
+    // reload level files when they change on disk
+    #[cfg(feature = "dev")]
+    asset_server.watch_for_changes().unwrap();
+
     // load audio
     asset_server.load_untyped("sounds/jump.ogg");
     asset_server.load_untyped("sounds/land.ogg");
@@ -92,23 +118,13 @@ fn setup(
 
     commands.spawn_bundle(UiCameraBundle::default());
 
-    // configure the window/texture dimensions
-    let dims: Dims = (WINDOW_WIDTH, WINDOW_HEIGHT, TEXTURE_STRIDE).into();
+    // configure the grid dimensions, these are fitted to the window by `update_camera`
+    // and resized to fit each level by `spawn_level`
+    let dims = Dims::new(VIEW_SIZE.0, VIEW_SIZE.1, TEXTURE_STRIDE);
     commands.insert_resource(dims);
 
-    // create the texture to display
-    let black = vec![0, 0, 0, 0];
-    let mut initial: Vec<u8> = vec![];
-    let bounds = dims.texture_values() / 4;
-    for _ in 0..bounds {
-        initial.append(&mut black.clone());
-    }
-    let texture = Texture::new(
-        Extent3d::new(dims.tex_w, dims.tex_h, 1),
-        bevy::render::texture::TextureDimension::D2,
-        initial,
-        TEXTURE_TYPE,
-    );
+    // create the particle simulation for the grid, along with the texture that displays it
+    let map = Map::new(&dims);
 
     // create a custom shader pipeline for the world sprite
     #[cfg(target_arch = "wasm32")]
@@ -122,7 +138,7 @@ fn setup(
     ));
 
     // spawn a sprite to display the texture and a resource to hold sprite data
-    let th = textures.add(texture.clone());
+    let th = textures.add(map.raw_texture.clone());
     let material = materials.add(th.clone().into());
 
     let main_handles = MainTexture { texture: th };
@@ -136,23 +152,31 @@ fn setup(
             render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
                 pipeline_handle,
             )]),
-            sprite: Sprite::new(Vec2::new(dims.win_w as f32, dims.win_h as f32)),
+            sprite: Sprite::new(Vec2::new(dims.world_w(), dims.world_h())),
             ..Default::default()
         })
         .insert(LightSource {
-            pos: Vec2::new(50., 20.),
-            strength: 30.,
             dims: Vec2::new(dims.tex_w as f32, dims.tex_h as f32),
+            ambient: 0.,
+            light_count: 0.,
+            lights: textures.add(light_texture(lighting.max_lights)),
         });
 
-    // create the map to track entities
-    let map = Map::new(dims, texture);
     commands.insert_resource(map);
 
     // move to loading state
     state.set(GameState::Loading).unwrap();
 }
 
+/// The value following the given flag on the command line, e.g. `--seed 42`
+fn arg_value(flag: &str) -> Option<String> {
+    let args = std::env::args().collect::<Vec<_>>();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|idx| args.get(idx + 1))
+        .cloned()
+}
+
 fn set_panic_hook() {
     #[cfg(target_arch = "wasm32")]
     console_error_panic_hook::set_once();